@@ -13,9 +13,11 @@ pub struct Cvs {
     pins: Pins,
 }
 
-#[derive(Default, defmt::Format)]
+#[derive(defmt::Format)]
 pub struct Cv {
     value: Option<f32>,
+    offset_compensation: f32,
+    scale_compensation: f32,
 }
 
 #[derive(defmt::Format)]
@@ -34,7 +36,14 @@ pub type Cv4Pin = gpio::gpioa::PA7<gpio::Analog>;
 impl Cvs {
     pub fn new(pins: Pins) -> Self {
         Self {
-            cvs: [Cv::default(), Cv::default(), Cv::default(), Cv::default()],
+            // NOTE: The compensation is measured per unit and loaded from
+            // the save, see `set_calibration`.
+            cvs: [
+                Cv::new(0.0, 1.0),
+                Cv::new(0.0, 1.0),
+                Cv::new(0.0, 1.0),
+                Cv::new(0.0, 1.0),
+            ],
             pins,
         }
     }
@@ -55,6 +64,18 @@ impl Cvs {
         self.cvs[3].set(sample_4, adc_2.slope());
     }
 
+    /// Set compensation making sure that control value can hit both
+    /// extremes and that V/oct tracks accurately.
+    pub fn set_calibration(
+        &mut self,
+        cv: usize,
+        offset_compensation: f32,
+        scale_compensation: f32,
+    ) {
+        self.cvs[cv].offset_compensation = offset_compensation;
+        self.cvs[cv].scale_compensation = scale_compensation;
+    }
+
     pub fn values(&self) -> [Option<f32>; CVS] {
         [
             self.cvs[0].value,
@@ -66,23 +87,35 @@ impl Cvs {
 }
 
 impl Cv {
+    fn new(offset_compensation: f32, scale_compensation: f32) -> Self {
+        Self {
+            value: None,
+            offset_compensation,
+            scale_compensation,
+        }
+    }
+
     fn set(&mut self, sample: u32, slope: u32) {
-        let value = transpose_adc(sample, slope);
+        let value = transpose_adc(
+            sample,
+            slope,
+            self.offset_compensation,
+            self.scale_compensation,
+        );
         self.value = Some(value);
     }
 }
 
-fn transpose_adc(sample: u32, slope: u32) -> f32 {
+fn transpose_adc(
+    sample: u32,
+    slope: u32,
+    offset_compensation: f32,
+    scale_compensation: f32,
+) -> f32 {
     // NOTE: The CV input theoretically spans between -5 and +5 V.
     let min = -5.0;
     let span = 10.0;
 
-    // NOTE: Based on the measuring, most of the CV inputs actually rest at -0.02.
-    let offset_compensation = 0.02;
-    // NOTE: The real span of measured CV is -4.98 to +4.98 V. This compensation
-    // makes sure that control value can hit both extremes.
-    let scale_compensation = 10.0 / (2.0 * 4.98);
-
     let phase = (slope as f32 - sample as f32) / slope as f32;
     let scaled = min + phase * span;
     ((scaled + offset_compensation) * scale_compensation).clamp(min, min + span)
@@ -1,9 +1,18 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::system::hal::gpio;
+use crate::system::hal::pac::EXTI;
 
 use super::debouncer::Debouncer;
 
 pub const GATES: usize = 2;
 
+// NOTE: Rising edges are latched by an interrupt, so even pulses shorter
+// than the sampling period, or those filtered out by the debouncer, get
+// registered.
+static RISING_EDGES: [AtomicBool; GATES] = [AtomicBool::new(false), AtomicBool::new(false)];
+const EXTI_LINES: [u32; GATES] = [13, 14];
+
 #[derive(defmt::Format)]
 pub struct Gates {
     triggers: [Trigger; GATES],
@@ -13,9 +22,13 @@ pub struct Gates {
 #[derive(Debug, defmt::Format)]
 pub struct Trigger {
     active: bool,
-    debouncer: Debouncer<4>,
+    triggered: bool,
+    hold: usize,
+    debouncer: Debouncer<DEBOUNCE>,
 }
 
+const DEBOUNCE: usize = 4;
+
 #[derive(defmt::Format)]
 pub struct Pins {
     pub gate_1: Trigger1Pin,
@@ -34,13 +47,40 @@ impl Gates {
     }
 
     pub fn sample(&mut self) {
-        self.triggers[0].set(self.pins.gate_1.is_high());
-        self.triggers[1].set(self.pins.gate_2.is_high());
+        let edges = [
+            RISING_EDGES[0].swap(false, Ordering::Relaxed),
+            RISING_EDGES[1].swap(false, Ordering::Relaxed),
+        ];
+        self.triggers[0].set(self.pins.gate_1.is_high(), edges[0]);
+        self.triggers[1].set(self.pins.gate_2.is_high(), edges[1]);
     }
 
     pub fn values(&self) -> [bool; GATES] {
         [self.triggers[0].active, self.triggers[1].active]
     }
+
+    /// Whether the gate went high with this sample.
+    pub fn triggers(&self) -> [bool; GATES] {
+        [self.triggers[0].triggered, self.triggers[1].triggered]
+    }
+}
+
+/// Latch rising edges of gate inputs.
+///
+/// This is to be called from the EXTI15_10 interrupt, configured to fire
+/// on rising edges of gate pins.
+pub fn handle_interrupt() {
+    // SAFETY: Only pending bits of gate lines are touched, and clearing
+    // them is an atomic write.
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = exti.cpupr1.read().bits();
+    for (edge, line) in RISING_EDGES.iter().zip(EXTI_LINES) {
+        let mask = 1 << line;
+        if pending & mask != 0 {
+            exti.cpupr1.write(|w| unsafe { w.bits(mask) });
+            edge.store(true, Ordering::Relaxed);
+        }
+    }
 }
 
 impl Trigger {
@@ -48,10 +88,28 @@ impl Trigger {
         Self {
             debouncer: Debouncer::new(),
             active: false,
+            triggered: false,
+            hold: 0,
         }
     }
 
-    fn set(&mut self, is_high: bool) {
-        self.active = self.debouncer.update(is_high);
+    fn set(&mut self, is_high: bool, edge: bool) {
+        let was_active = self.active;
+        let debounced = self.debouncer.update(is_high);
+
+        // NOTE: The debounced level lags a couple of samples behind the
+        // latched edge. The edge holds the gate high until the level
+        // catches up, so it rises only once. Pulses too short to pass the
+        // debouncer are held for the length of its window.
+        if edge && !was_active {
+            self.hold = DEBOUNCE;
+        }
+        if debounced {
+            self.hold = 0;
+        }
+        self.active = debounced || self.hold > 0;
+        self.hold = self.hold.saturating_sub(1);
+
+        self.triggered = self.active && !was_active;
     }
 }
@@ -8,7 +8,7 @@ mod switch;
 
 pub use self::buttons::Pins as ButtonsPins;
 pub use self::cvs::Pins as CvsPins;
-pub use self::gates::Pins as GatesPins;
+pub use self::gates::{handle_interrupt as handle_gates_interrupt, Pins as GatesPins};
 pub use self::pots::Pins as PotsPins;
 pub use self::switch::Pins as SwitchPins;
 
@@ -25,6 +25,7 @@ pub struct ControlInputSnapshot {
     pub buttons: [bool; BUTTONS],
     pub cvs: [Option<f32>; CVS],
     pub gates: [bool; GATES],
+    pub gate_triggers: [bool; GATES],
     pub switch: u8,
 }
 
@@ -69,12 +70,17 @@ impl ControlInputInterface {
         self.switch.sample();
     }
 
+    pub fn set_cv_calibration(&mut self, cv: usize, offset: f32, scale: f32) {
+        self.cvs.set_calibration(cv, offset, scale);
+    }
+
     pub fn snapshot(&self) -> ControlInputSnapshot {
         ControlInputSnapshot {
             pots: self.pots.values(),
             buttons: self.buttons.values(),
             cvs: self.cvs.values(),
             gates: self.gates.values(),
+            gate_triggers: self.gates.triggers(),
             switch: self.switch.value(),
         }
     }
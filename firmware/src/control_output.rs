@@ -13,6 +13,7 @@ pub struct ControlOutputState {
 pub struct ControlOutputInterface {
     pins: Pins,
     dac: (C1<DAC, Enabled>, C2<DAC, Enabled>),
+    cv_calibrations: [Calibration; 2],
 }
 
 pub struct Config {
@@ -26,6 +27,11 @@ pub struct Pins {
     pub gates: (Gate1, Gate2),
 }
 
+struct Calibration {
+    offset: f32,
+    scale: f32,
+}
+
 type Led1 = gpio::gpiob::PB15<gpio::Output>;
 type Led2 = gpio::gpiob::PB14<gpio::Output>;
 type Led3 = gpio::gpiob::PB8<gpio::Output>;
@@ -39,9 +45,16 @@ impl ControlOutputInterface {
         Self {
             pins: config.pins,
             dac: config.dac,
+            // NOTE: The calibration is measured per unit and loaded from the
+            // save, see `set_cv_calibration`.
+            cv_calibrations: [Calibration::new(0.0, 1.0), Calibration::new(0.0, 1.0)],
         }
     }
 
+    pub fn set_cv_calibration(&mut self, cv: usize, offset: f32, scale: f32) {
+        self.cv_calibrations[cv] = Calibration::new(offset, scale);
+    }
+
     pub fn set_state(&mut self, state: &ControlOutputState) {
         self.pins.leds.0.set_state(state.leds[0].into());
         self.pins.leds.1.set_state(state.leds[1].into());
@@ -51,8 +64,20 @@ impl ControlOutputInterface {
         self.pins.gates.0.set_state(state.gates[0].into());
         self.pins.gates.1.set_state(state.gates[1].into());
 
-        self.dac.1.set_value(f32_cv_to_u16(state.cvs[0]));
-        self.dac.0.set_value(f32_cv_to_u16(state.cvs[1]));
+        let cv_1 = self.cv_calibrations[0].apply(state.cvs[0]);
+        let cv_2 = self.cv_calibrations[1].apply(state.cvs[1]);
+        self.dac.1.set_value(f32_cv_to_u16(cv_1));
+        self.dac.0.set_value(f32_cv_to_u16(cv_2));
+    }
+}
+
+impl Calibration {
+    fn new(offset: f32, scale: f32) -> Self {
+        Self { offset, scale }
+    }
+
+    fn apply(&self, value: f32) -> f32 {
+        value * self.scale + self.offset
     }
 }
 
@@ -60,6 +85,8 @@ fn f32_cv_to_u16(value: f32) -> u16 {
     const OUT_MIN: f32 = 0.0;
     const OUT_MAX: f32 = 5.0;
     let desired = (value - OUT_MIN) / (OUT_MAX - OUT_MIN);
-    let scaled = (desired * 4096.0).clamp(0.0, 4095.999);
+    // NOTE: Round to the nearest step instead of truncating, so V/oct
+    // output is not biased down by half a step.
+    let scaled = (desired * 4096.0 + 0.5).clamp(0.0, 4095.0);
     scaled as u16
 }
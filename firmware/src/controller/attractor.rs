@@ -0,0 +1,168 @@
+//! Chaotic attractors integrated at the control rate.
+
+/// Integrates one of the chaotic systems with the 4th order Runge-Kutta
+/// method, splitting each tick into steps small enough to stay stable.
+pub struct Attractor {
+    system: System,
+    state: [f32; 3],
+    speed: f32,
+    chaos: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum System {
+    Lorenz,
+    Rossler,
+    Chua,
+}
+
+impl Attractor {
+    const MAX_STEP: f32 = 0.01;
+    const MAX_STEPS: usize = 16;
+    // NOTE: Way beyond the range any of the systems reaches when it is
+    // stable. If this is crossed, the system diverged and must be reset.
+    const LIMIT: f32 = 1000.0;
+
+    pub fn new() -> Self {
+        Self {
+            system: System::Lorenz,
+            state: System::Lorenz.initial_state(),
+            speed: 1.0,
+            chaos: 0.5,
+        }
+    }
+
+    pub fn set_system(&mut self, system: System) {
+        if system != self.system {
+            self.system = system;
+            self.reset();
+        }
+    }
+
+    /// Set speed as a multiple of the natural speed of the system.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Set the parameter driving the system into chaos, between 0.0 and
+    /// 1.0. The lower end is periodic or settles, the upper end is fully
+    /// chaotic.
+    pub fn set_chaos(&mut self, chaos: f32) {
+        self.chaos = chaos;
+    }
+
+    pub fn reset(&mut self) {
+        self.state = self.system.initial_state();
+    }
+
+    /// Advance the system by one tick.
+    pub fn tick(&mut self) {
+        let parameter = self.system.parameter(self.chaos);
+        let duration = self.speed * self.system.time_scale() / super::CONTROL_RATE;
+        let steps = (libm::ceilf(duration / Self::MAX_STEP) as usize).clamp(1, Self::MAX_STEPS);
+        let step = duration / steps as f32;
+
+        for _ in 0..steps {
+            self.state = rk4(self.system, parameter, self.state, step);
+        }
+
+        let diverged = self
+            .state
+            .iter()
+            .any(|v| !v.is_finite() || libm::fabsf(*v) > Self::LIMIT);
+        if diverged {
+            self.reset();
+        }
+    }
+
+    /// X of the system, normalized roughly between -1.0 and 1.0.
+    pub fn x(&self) -> f32 {
+        (self.state[0] / self.system.ranges()[0]).clamp(-1.0, 1.0)
+    }
+
+    /// Y of the system, normalized roughly between -1.0 and 1.0.
+    pub fn y(&self) -> f32 {
+        (self.state[1] / self.system.ranges()[1]).clamp(-1.0, 1.0)
+    }
+}
+
+impl System {
+    fn initial_state(self) -> [f32; 3] {
+        match self {
+            Self::Lorenz => [1.0, 1.0, 1.0],
+            Self::Rossler => [1.0, 1.0, 0.0],
+            Self::Chua => [0.7, 0.0, 0.0],
+        }
+    }
+
+    /// Map chaos to ρ of Lorenz, c of Rössler or α of Chua.
+    fn parameter(self, chaos: f32) -> f32 {
+        let (min, max) = match self {
+            Self::Lorenz => (14.0, 40.0),
+            Self::Rossler => (2.5, 8.0),
+            Self::Chua => (8.0, 16.0),
+        };
+        min + chaos * (max - min)
+    }
+
+    // NOTE: Makes the systems orbit at similar speeds.
+    fn time_scale(self) -> f32 {
+        match self {
+            Self::Lorenz => 1.0,
+            Self::Rossler => 5.0,
+            Self::Chua => 3.0,
+        }
+    }
+
+    fn ranges(self) -> [f32; 2] {
+        match self {
+            Self::Lorenz => [20.0, 27.0],
+            Self::Rossler => [12.0, 12.0],
+            Self::Chua => [2.5, 0.5],
+        }
+    }
+
+    fn derivative(self, parameter: f32, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Lorenz => {
+                const SIGMA: f32 = 10.0;
+                const BETA: f32 = 8.0 / 3.0;
+                [SIGMA * (y - x), x * (parameter - z) - y, x * y - BETA * z]
+            }
+            Self::Rossler => {
+                const A: f32 = 0.2;
+                const B: f32 = 0.2;
+                [-y - z, x + A * y, B + z * (x - parameter)]
+            }
+            Self::Chua => {
+                const BETA: f32 = 28.0;
+                const M0: f32 = -8.0 / 7.0;
+                const M1: f32 = -5.0 / 7.0;
+                let diode =
+                    M1 * x + 0.5 * (M0 - M1) * (libm::fabsf(x + 1.0) - libm::fabsf(x - 1.0));
+                [parameter * (y - x - diode), x - y + z, -BETA * y]
+            }
+        }
+    }
+}
+
+fn rk4(system: System, parameter: f32, state: [f32; 3], step: f32) -> [f32; 3] {
+    let offset = |base: [f32; 3], delta: [f32; 3], factor: f32| {
+        [
+            base[0] + delta[0] * factor,
+            base[1] + delta[1] * factor,
+            base[2] + delta[2] * factor,
+        ]
+    };
+
+    let k1 = system.derivative(parameter, state);
+    let k2 = system.derivative(parameter, offset(state, k1, step / 2.0));
+    let k3 = system.derivative(parameter, offset(state, k2, step / 2.0));
+    let k4 = system.derivative(parameter, offset(state, k3, step));
+
+    let mut next = state;
+    for i in 0..3 {
+        next[i] += step / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+    }
+    next
+}
@@ -0,0 +1,97 @@
+use crate::control_output::ControlOutputState;
+
+/// Selects a bank of modes, letting the 8-position switch reach more than
+/// 8 of them.
+///
+/// Holding both buttons for a second moves to the next bank. The selected
+/// bank is then shown on the LEDs for a second.
+///
+/// Buttons are also filtered before they reach modes, so the gesture does
+/// not trigger actions of the selected mode. A press is passed on only
+/// after it was held for a moment without the other button joining it. A
+/// shorter tap is passed on once it is released. While both buttons are
+/// down, and until both of them are released, modes see no button pressed.
+pub struct BankSelector {
+    bank: u8,
+    banks: u8,
+    hold_ticks: u32,
+    display_ticks: u32,
+    gesture: bool,
+    press_ticks: [u32; 2],
+}
+
+impl BankSelector {
+    const HOLD: u32 = 1000;
+    const DISPLAY: u32 = 1000;
+    // NOTE: Time the other button has to join the gesture.
+    const GRACE: u32 = 50;
+
+    pub fn new(banks: u8) -> Self {
+        Self {
+            bank: 0,
+            banks,
+            hold_ticks: 0,
+            display_ticks: 0,
+            gesture: false,
+            press_ticks: [0, 0],
+        }
+    }
+
+    /// Process buttons, returning their state as it should be seen by
+    /// modes.
+    pub fn apply_buttons(&mut self, buttons: [bool; 2]) -> [bool; 2] {
+        self.detect_gesture(buttons);
+
+        if buttons[0] && buttons[1] {
+            self.gesture = true;
+        } else if !buttons[0] && !buttons[1] {
+            self.gesture = false;
+        }
+
+        if self.gesture {
+            self.press_ticks = [0, 0];
+            return [false, false];
+        }
+
+        let mut filtered = [false, false];
+        for (i, pressed) in buttons.iter().enumerate() {
+            if *pressed {
+                self.press_ticks[i] = self.press_ticks[i].saturating_add(1);
+                filtered[i] = self.press_ticks[i] > Self::GRACE;
+            } else {
+                // NOTE: A tap released before the grace period passes is
+                // reported as pressed for a single tick.
+                filtered[i] = self.press_ticks[i] > 0 && self.press_ticks[i] <= Self::GRACE;
+                self.press_ticks[i] = 0;
+            }
+        }
+        filtered
+    }
+
+    fn detect_gesture(&mut self, buttons: [bool; 2]) {
+        if buttons[0] && buttons[1] {
+            self.hold_ticks += 1;
+            if self.hold_ticks == Self::HOLD {
+                self.bank = (self.bank + 1) % self.banks;
+                self.display_ticks = Self::DISPLAY;
+                defmt::info!("Switching to bank={:?}", self.bank);
+            }
+        } else {
+            self.hold_ticks = 0;
+        }
+    }
+
+    pub fn bank(&self) -> u8 {
+        self.bank
+    }
+
+    /// Override LEDs to show the bank after it was changed.
+    pub fn tick(&mut self, state: &mut ControlOutputState) {
+        if self.display_ticks > 0 {
+            self.display_ticks -= 1;
+            for (i, led) in state.leds.iter_mut().enumerate() {
+                *led = i == self.bank as usize;
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+/// Internal clock shared by all the modes.
+///
+/// Its speed is set in the utilities mode and it keeps running while other
+/// modes are selected, so they can be synced to it. The speed is persisted,
+/// before it is first set, the clock runs at 2 beats per second.
+pub struct Clock {
+    phase: f32,
+    speed: f32,
+    beat: bool,
+}
+
+/// Follows triggers of an external clock, falling back to the internal
+/// clock when no trigger arrived recently.
+pub struct ClockFollower {
+    triggered: bool,
+    ticks_since_trigger: u32,
+    period: u32,
+}
+
+impl Clock {
+    pub const DEFAULT_SPEED: f32 = 2.0;
+
+    pub fn new() -> Self {
+        Self {
+            phase: 0.0,
+            speed: Self::DEFAULT_SPEED,
+            beat: false,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Set speed of the clock in beats per second.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn tick(&mut self) {
+        self.phase += self.speed / super::CONTROL_RATE;
+        self.beat = self.phase >= 1.0;
+        if self.beat {
+            (self.phase, _) = libm::modff(self.phase);
+        }
+    }
+
+    /// Length of a beat in ticks.
+    pub fn period(&self) -> f32 {
+        super::CONTROL_RATE / self.speed
+    }
+
+    /// Position within the current beat, between 0.0 and 1.0.
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Whether the clock crossed a beat during the last tick.
+    pub fn beat(&self) -> bool {
+        self.beat
+    }
+}
+
+impl ClockFollower {
+    // NOTE: The internal clock takes over if no external trigger arrived
+    // in 4 seconds.
+    const TIMEOUT: u32 = 4000;
+
+    pub fn new() -> Self {
+        Self {
+            triggered: false,
+            ticks_since_trigger: u32::MAX,
+            period: 0,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.triggered = true;
+    }
+
+    /// Returns true if the followed clock crossed a beat.
+    pub fn tick(&mut self, clock: &Clock) -> bool {
+        self.ticks_since_trigger = self.ticks_since_trigger.saturating_add(1);
+
+        if self.triggered {
+            self.triggered = false;
+            // NOTE: The period measured before the external clock timed out
+            // is not relevant anymore.
+            self.period = if self.is_external() {
+                self.ticks_since_trigger
+            } else {
+                0
+            };
+            self.ticks_since_trigger = 0;
+            return true;
+        }
+
+        if self.is_external() {
+            false
+        } else {
+            clock.beat()
+        }
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.ticks_since_trigger < Self::TIMEOUT
+    }
+
+    /// Returns false if the external clock just started and its period
+    /// was not measured yet.
+    pub fn is_measured(&self) -> bool {
+        !self.is_external() || self.period > 0
+    }
+
+    /// Length of a beat in ticks.
+    pub fn period(&self, clock: &Clock) -> f32 {
+        if self.is_external() && self.period > 0 {
+            self.period as f32
+        } else {
+            clock.period()
+        }
+    }
+
+    /// Position within the current beat, between 0.0 and 1.0.
+    pub fn phase(&self, clock: &Clock) -> f32 {
+        if self.is_external() {
+            if self.period > 0 {
+                // NOTE: The phase stays at the end of the beat if the next
+                // trigger arrives late.
+                (self.ticks_since_trigger as f32 / self.period as f32).min(0.999)
+            } else {
+                0.0
+            }
+        } else {
+            clock.phase()
+        }
+    }
+}
@@ -0,0 +1,17 @@
+#[derive(Default)]
+pub struct EdgeDetector {
+    last: bool,
+}
+
+impl EdgeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true when the value goes from low to high.
+    pub fn rising(&mut self, value: bool) -> bool {
+        let rising = value && !self.last;
+        self.last = value;
+        rising
+    }
+}
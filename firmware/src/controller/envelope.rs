@@ -0,0 +1,144 @@
+//! ADSR envelope generator running at the control rate.
+
+pub struct Envelope {
+    stage: Stage,
+    level: f32,
+    start: f32,
+    progress: f32,
+    attack: f32,
+    decay: f32,
+    sustain: f32,
+    release: f32,
+    curve: Curve,
+    looping: bool,
+    gate: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    Linear,
+    /// Fast start with a slow tail, like a capacitor charging.
+    Exponential,
+    /// Slow start with a fast end.
+    Logarithmic,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self {
+            stage: Stage::Idle,
+            level: 0.0,
+            start: 0.0,
+            progress: 0.0,
+            attack: 0.01,
+            decay: 0.1,
+            sustain: 0.5,
+            release: 0.1,
+            curve: Curve::Linear,
+            looping: false,
+            gate: false,
+        }
+    }
+
+    /// Set attack, decay and release in seconds and sustain between 0.0
+    /// and 1.0.
+    pub fn set_adsr(&mut self, attack: f32, decay: f32, sustain: f32, release: f32) {
+        self.attack = attack;
+        self.decay = decay;
+        self.sustain = sustain;
+        self.release = release;
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.curve = curve;
+    }
+
+    /// When looping, the envelope cycles through attack and decay down to
+    /// zero for as long as the gate is held, skipping sustain.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Start the attack from the current level.
+    pub fn trigger(&mut self) {
+        self.enter(Stage::Attack);
+    }
+
+    /// Start the attack from zero.
+    pub fn retrigger(&mut self) {
+        self.level = 0.0;
+        self.enter(Stage::Attack);
+    }
+
+    pub fn set_gate(&mut self, gate: bool) {
+        if self.gate && !gate && self.stage != Stage::Idle {
+            self.enter(Stage::Release);
+        }
+        self.gate = gate;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.stage != Stage::Idle
+    }
+
+    /// Advance by one tick, returning the level between 0.0 and 1.0.
+    pub fn tick(&mut self) -> f32 {
+        let (duration, target) = match self.stage {
+            Stage::Idle => return self.level,
+            Stage::Sustain => {
+                self.level = self.sustain;
+                return self.level;
+            }
+            Stage::Attack => (self.attack, 1.0),
+            Stage::Decay if self.looping => (self.decay, 0.0),
+            Stage::Decay => (self.decay, self.sustain),
+            Stage::Release => (self.release, 0.0),
+        };
+
+        self.progress += 1.0 / (duration * super::CONTROL_RATE).max(1.0);
+        if self.progress >= 1.0 {
+            self.level = target;
+            let next = match self.stage {
+                Stage::Attack => Stage::Decay,
+                Stage::Decay if self.looping && self.gate => Stage::Attack,
+                Stage::Decay if self.looping => Stage::Idle,
+                Stage::Decay => Stage::Sustain,
+                _ => Stage::Idle,
+            };
+            self.enter(next);
+        } else {
+            let shaped = self.curve.shape(self.progress);
+            self.level = self.start + (target - self.start) * shaped;
+        }
+
+        self.level
+    }
+
+    fn enter(&mut self, stage: Stage) {
+        self.stage = stage;
+        self.start = self.level;
+        self.progress = 0.0;
+    }
+}
+
+impl Curve {
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            Self::Linear => x,
+            Self::Exponential => {
+                let inverted = 1.0 - x;
+                1.0 - inverted * inverted * inverted
+            }
+            Self::Logarithmic => x * x * x,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Discrete steps selected by pots and CVs, kept from jittering between
+//! two neighbouring values.
+
+// NOTE: The value must move over a little more than a half of a step to
+// change it.
+const HYSTERESIS: f32 = 0.6;
+
+/// Round the value to a whole step, keeping the current one unless the
+/// value moved far enough from it.
+pub fn step_with_hysteresis(current: i32, value: f32) -> i32 {
+    if libm::fabsf(value - current as f32) > HYSTERESIS {
+        libm::roundf(value) as i32
+    } else {
+        current
+    }
+}
+
+/// Select one of `count` equally wide zones of a value between 0.0 and
+/// 1.0, keeping the current one until the value gets a little past its
+/// border.
+pub fn zone_with_hysteresis(current: usize, value: f32, count: usize) -> usize {
+    let step = step_with_hysteresis(current as i32, value * count as f32 - 0.5);
+    step.clamp(0, count as i32 - 1) as usize
+}
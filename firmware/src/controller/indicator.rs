@@ -0,0 +1,38 @@
+use super::output::Outputs;
+
+/// Briefly shows an option selected by a button on the LEDs.
+pub struct Indicator {
+    option: usize,
+    countdown: u32,
+}
+
+impl Indicator {
+    const DURATION: u32 = 1000;
+
+    pub fn new() -> Self {
+        Self {
+            option: 0,
+            countdown: 0,
+        }
+    }
+
+    pub fn indicate(&mut self, option: usize) {
+        self.option = option;
+        self.countdown = Self::DURATION;
+    }
+
+    /// Light up the LED of the selected option, options past the last LED
+    /// light up all of them. Returns false once the indication is over and
+    /// LEDs can be used by the mode again.
+    pub fn tick(&mut self, outputs: &mut Outputs) -> bool {
+        if self.countdown == 0 {
+            return false;
+        }
+        self.countdown -= 1;
+        let all = self.option >= outputs.leds.len();
+        for (i, led) in outputs.leds.iter_mut().enumerate() {
+            led.set(all || i == self.option);
+        }
+        true
+    }
+}
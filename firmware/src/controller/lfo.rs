@@ -0,0 +1,135 @@
+//! Low frequency oscillator running at the control rate.
+
+use core::f32::consts::PI;
+
+use super::output::LinearOutput;
+use crate::random_generator::RandomGenerator;
+
+pub struct Lfo {
+    phase: f32,
+    frequency: f32,
+    waveform: Waveform,
+    pulse_width: f32,
+    amplitude: f32,
+    offset: f32,
+    random_previous: f32,
+    random_next: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    RampUp,
+    RampDown,
+    Square,
+    SteppedRandom,
+    SmoothRandom,
+}
+
+impl Lfo {
+    pub fn new() -> Self {
+        Self {
+            phase: 0.0,
+            frequency: 1.0,
+            waveform: Waveform::Sine,
+            pulse_width: 0.5,
+            amplitude: 0.0,
+            offset: 0.0,
+            random_previous: 0.0,
+            random_next: 0.0,
+        }
+    }
+
+    /// Set frequency in Hz.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency;
+    }
+
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    /// Set the portion of the cycle the square spends high, between 0.0
+    /// and 1.0.
+    pub fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.pulse_width = pulse_width;
+    }
+
+    /// Set the span of the waveform in volts.
+    pub fn set_amplitude(&mut self, amplitude: f32) {
+        self.amplitude = amplitude;
+    }
+
+    /// Set the bottom of the waveform in volts.
+    pub fn set_offset(&mut self, offset: f32) {
+        self.offset = offset;
+    }
+
+    /// Advance the oscillator by one tick. Returns true when a new cycle
+    /// starts.
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator) -> bool {
+        let (phase, _) = libm::modff(self.phase + self.frequency / super::CONTROL_RATE);
+        self.sync(phase, random_generator)
+    }
+
+    /// Move the oscillator to the given phase, e.g. when it is locked to
+    /// an external clock. Returns true when a new cycle starts.
+    pub fn sync(&mut self, phase: f32, random_generator: &mut RandomGenerator) -> bool {
+        let new_cycle = phase < self.phase;
+        if new_cycle {
+            self.random_previous = self.random_next;
+            self.random_next = random_generator.f32();
+        }
+        self.phase = phase;
+        new_cycle
+    }
+
+    /// Current value in volts.
+    pub fn value(&self) -> f32 {
+        self.offset + self.amplitude * self.unipolar_value()
+    }
+
+    pub fn write(&self, output: &mut LinearOutput) {
+        output.set_value(self.value());
+    }
+
+    // NOTE: All the periodic shapes but the ramp down start at their
+    // bottom. The ramp down starts at its top. Either way, the start of the
+    // cycle is easy to recognize.
+    fn unipolar_value(&self) -> f32 {
+        let phase = self.phase;
+        match self.waveform {
+            Waveform::Sine => 0.5 - 0.5 * libm::cosf(2.0 * PI * phase),
+            Waveform::Triangle => 1.0 - libm::fabsf(2.0 * phase - 1.0),
+            Waveform::RampUp => phase,
+            Waveform::RampDown => 1.0 - phase,
+            Waveform::Square => {
+                if phase < 1.0 - self.pulse_width {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Waveform::SteppedRandom => self.random_next,
+            Waveform::SmoothRandom => {
+                let x = 0.5 - 0.5 * libm::cosf(PI * phase);
+                self.random_previous + (self.random_next - self.random_previous) * x
+            }
+        }
+    }
+}
+
+impl Waveform {
+    pub fn from_pot(value: f32) -> Self {
+        match (value * 6.99) as usize {
+            0 => Self::Sine,
+            1 => Self::Triangle,
+            2 => Self::RampUp,
+            3 => Self::RampDown,
+            4 => Self::Square,
+            5 => Self::SteppedRandom,
+            _ => Self::SmoothRandom,
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Control logic of the module.
+//!
+//! The 8-position switch selects one of the modes within the current bank.
+//! Holding both buttons for a second moves to the next bank. Each mode maps
+//! pots, CV and gate inputs to the outputs differently, see documentation
+//! of the respective module. The last position of the last bank is used to
+//! calibrate CV inputs and outputs.
+
+mod attractor;
+mod bank_selector;
+mod clock;
+mod edge_detector;
+mod envelope;
+mod hysteresis;
+mod indicator;
+mod lfo;
+mod modes;
+mod output;
+mod output_mapping;
+mod patch_detector;
+mod pot_latch;
+mod quantizer;
+mod save;
+mod scheduler;
+mod slew;
+
+use core::mem::MaybeUninit;
+
+use self::bank_selector::BankSelector;
+use self::clock::Clock;
+use self::modes::arpeggiator::Arpeggiator;
+use self::modes::bernoulli::Bernoulli;
+use self::modes::burst::Burst;
+use self::modes::calibrator::Calibrator;
+use self::modes::chaos::Chaos;
+use self::modes::comparator::Comparator;
+use self::modes::cv_math::CvMath;
+use self::modes::envelope::Envelope;
+use self::modes::euclidean::Euclidean;
+use self::modes::gate_looper::GateLooper;
+use self::modes::gate_utilities::GateUtilities;
+use self::modes::lfo::LfoMode;
+use self::modes::logic::Logic;
+use self::modes::quantizer::Quantizer;
+use self::modes::random::Random;
+use self::modes::recorder::Recorder;
+use self::modes::sample_and_hold::SampleAndHold;
+use self::modes::sequencer::Sequencer;
+use self::modes::sequential_switch::SequentialSwitch;
+use self::modes::slew::Slew;
+use self::modes::synced_lfo::SyncedLfo;
+use self::modes::transposer::Transposer;
+use self::modes::turing_machine::TuringMachine;
+use self::modes::utilities::Utilities;
+use self::output::Outputs;
+use crate::control_input::ControlInputSnapshot;
+use crate::control_output::ControlOutputState;
+use crate::random_generator::RandomGenerator;
+
+pub use self::save::{Calibrations, Save, SAVE_SIZE};
+
+// NOTE: Both `apply_input_snapshot` and `tick` are expected to be called
+// with 1 kHz frequency.
+const CONTROL_RATE: f32 = 1000.0;
+
+const BANKS: u8 = 3;
+
+// NOTE: Changes get persisted once they settle, not to wear the flash out.
+const SAVE_DELAY: u32 = 2000;
+const CLOCK_SPEED_THRESHOLD: f32 = 0.05;
+
+pub struct Controller {
+    mode: Mode,
+    bank_selector: BankSelector,
+    clock: Clock,
+    saved_clock_speed: f32,
+    save_countdown: u32,
+    save_requested: bool,
+    calibrations: Calibrations,
+    calibrations_changed: bool,
+    utilities: Utilities,
+    euclidean: Euclidean,
+    bernoulli: Bernoulli,
+    burst: Burst,
+    synced_lfo: SyncedLfo,
+    lfo: LfoMode,
+    sample_and_hold: SampleAndHold,
+    quantizer: Quantizer,
+    envelope: Envelope,
+    slew: Slew,
+    turing_machine: TuringMachine,
+    sequencer: Sequencer,
+    logic: Logic,
+    comparator: Comparator,
+    gate_utilities: GateUtilities,
+    sequential_switch: SequentialSwitch,
+    cv_math: CvMath,
+    random: Random,
+    chaos: Chaos,
+    recorder: Recorder,
+    gate_looper: GateLooper,
+    arpeggiator: Arpeggiator,
+    transposer: Transposer,
+    calibrator: Calibrator,
+    outputs: Outputs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, defmt::Format)]
+enum Mode {
+    Utilities,
+    Euclidean,
+    Bernoulli,
+    Burst,
+    SyncedLfo,
+    Lfo,
+    SampleAndHold,
+    Quantizer,
+    Envelope,
+    Slew,
+    TuringMachine,
+    Sequencer,
+    Logic,
+    Comparator,
+    GateUtilities,
+    SequentialSwitch,
+    CvMath,
+    Random,
+    Chaos,
+    Recorder,
+    GateLooper,
+    Arpeggiator,
+    Transposer,
+    Calibrator,
+}
+
+impl Controller {
+    /// The memory is used by the CV recorder to store recorded gestures.
+    pub fn new(save: Save, memory: &'static mut [MaybeUninit<u32>]) -> Self {
+        let mut clock = Clock::new();
+        clock.set_speed(save.clock_speed);
+
+        Self {
+            mode: Mode::Utilities,
+            bank_selector: BankSelector::new(BANKS),
+            clock,
+            saved_clock_speed: save.clock_speed,
+            save_countdown: 0,
+            save_requested: false,
+            calibrations: save.calibrations,
+            calibrations_changed: false,
+            utilities: Utilities::new(),
+            euclidean: Euclidean::new(),
+            bernoulli: Bernoulli::new(),
+            burst: Burst::new(),
+            synced_lfo: SyncedLfo::new(),
+            lfo: LfoMode::new(),
+            sample_and_hold: SampleAndHold::new(),
+            quantizer: Quantizer::new(),
+            envelope: Envelope::new(),
+            slew: Slew::new(),
+            turing_machine: TuringMachine::new(),
+            sequencer: Sequencer::new(save.patterns),
+            logic: Logic::new(),
+            comparator: Comparator::new(),
+            gate_utilities: GateUtilities::new(),
+            sequential_switch: SequentialSwitch::new(),
+            cv_math: CvMath::new(),
+            random: Random::new(),
+            chaos: Chaos::new(),
+            recorder: Recorder::new(memory),
+            gate_looper: GateLooper::new(),
+            arpeggiator: Arpeggiator::new(),
+            transposer: Transposer::new(),
+            calibrator: Calibrator::new(save.calibrations),
+            outputs: Outputs::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, mut snapshot: ControlInputSnapshot) {
+        snapshot.buttons = self.bank_selector.apply_buttons(snapshot.buttons);
+
+        let mode = Mode::from_position(self.bank_selector.bank(), snapshot.switch);
+        if mode != self.mode {
+            defmt::info!("Switching to mode={:?}", mode);
+            self.mode = mode;
+            self.outputs = Outputs::new();
+            if mode == Mode::Sequencer {
+                self.sequencer.enter(&snapshot);
+            }
+        }
+
+        match self.mode {
+            Mode::Utilities => self
+                .utilities
+                .apply_input_snapshot(&snapshot, &mut self.clock),
+            Mode::Euclidean => self.euclidean.apply_input_snapshot(&snapshot),
+            Mode::Bernoulli => self.bernoulli.apply_input_snapshot(&snapshot),
+            Mode::Burst => self.burst.apply_input_snapshot(&snapshot),
+            Mode::SyncedLfo => self.synced_lfo.apply_input_snapshot(&snapshot),
+            Mode::Lfo => self.lfo.apply_input_snapshot(&snapshot),
+            Mode::SampleAndHold => self.sample_and_hold.apply_input_snapshot(&snapshot),
+            Mode::Quantizer => self.quantizer.apply_input_snapshot(&snapshot),
+            Mode::Envelope => self.envelope.apply_input_snapshot(&snapshot),
+            Mode::Slew => self.slew.apply_input_snapshot(&snapshot),
+            Mode::TuringMachine => self.turing_machine.apply_input_snapshot(&snapshot),
+            Mode::Sequencer => self.sequencer.apply_input_snapshot(&snapshot),
+            Mode::Logic => self.logic.apply_input_snapshot(&snapshot),
+            Mode::Comparator => self.comparator.apply_input_snapshot(&snapshot),
+            Mode::GateUtilities => self.gate_utilities.apply_input_snapshot(&snapshot),
+            Mode::SequentialSwitch => self.sequential_switch.apply_input_snapshot(&snapshot),
+            Mode::CvMath => self.cv_math.apply_input_snapshot(&snapshot),
+            Mode::Random => self.random.apply_input_snapshot(&snapshot),
+            Mode::Chaos => self.chaos.apply_input_snapshot(&snapshot),
+            Mode::Recorder => self.recorder.apply_input_snapshot(&snapshot),
+            Mode::GateLooper => self.gate_looper.apply_input_snapshot(&snapshot),
+            Mode::Arpeggiator => self.arpeggiator.apply_input_snapshot(&snapshot),
+            Mode::Transposer => self.transposer.apply_input_snapshot(&snapshot),
+            Mode::Calibrator => self.calibrator.apply_input_snapshot(&snapshot),
+        }
+    }
+
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator) -> ControlOutputState {
+        self.clock.tick();
+
+        match self.mode {
+            Mode::Utilities => self.utilities.tick(&self.clock, &mut self.outputs),
+            Mode::Euclidean => self.euclidean.tick(&self.clock, &mut self.outputs),
+            Mode::Bernoulli => self.bernoulli.tick(random_generator, &mut self.outputs),
+            Mode::Burst => self.burst.tick(&self.clock, &mut self.outputs),
+            Mode::SyncedLfo => {
+                self.synced_lfo
+                    .tick(&self.clock, random_generator, &mut self.outputs)
+            }
+            Mode::Lfo => self.lfo.tick(random_generator, &mut self.outputs),
+            Mode::SampleAndHold => self
+                .sample_and_hold
+                .tick(random_generator, &mut self.outputs),
+            Mode::Quantizer => self.quantizer.tick(&mut self.outputs),
+            Mode::Envelope => self.envelope.tick(&mut self.outputs),
+            Mode::Slew => self.slew.tick(&mut self.outputs),
+            Mode::TuringMachine => {
+                self.turing_machine
+                    .tick(&self.clock, random_generator, &mut self.outputs)
+            }
+            Mode::Sequencer => self.sequencer.tick(&self.clock, &mut self.outputs),
+            Mode::Logic => self.logic.tick(&mut self.outputs),
+            Mode::Comparator => self.comparator.tick(&mut self.outputs),
+            Mode::GateUtilities => self.gate_utilities.tick(&self.clock, &mut self.outputs),
+            Mode::SequentialSwitch => self
+                .sequential_switch
+                .tick(random_generator, &mut self.outputs),
+            Mode::CvMath => self.cv_math.tick(&mut self.outputs),
+            Mode::Random => self
+                .random
+                .tick(&self.clock, random_generator, &mut self.outputs),
+            Mode::Chaos => self.chaos.tick(&mut self.outputs),
+            Mode::Recorder => self.recorder.tick(&self.clock, &mut self.outputs),
+            Mode::GateLooper => self.gate_looper.tick(&self.clock, &mut self.outputs),
+            Mode::Arpeggiator => {
+                self.arpeggiator
+                    .tick(&self.clock, random_generator, &mut self.outputs)
+            }
+            Mode::Transposer => self.transposer.tick(&mut self.outputs),
+            Mode::Calibrator => self.calibrator.tick(&mut self.outputs),
+        }
+
+        if let Some(calibrations) = self.calibrator.take_calibrations() {
+            self.calibrations = calibrations;
+            self.calibrations_changed = true;
+            self.save_requested = true;
+        }
+
+        self.sequencer.tick_save();
+        self.schedule_clock_save();
+
+        self.outputs.tick();
+        let mut state = self.outputs.state();
+        self.bank_selector.tick(&mut state);
+        state
+    }
+
+    /// Gain of the left and right audio channel.
+    pub fn audio_gains(&self) -> [f32; 2] {
+        match self.mode {
+            Mode::Envelope => self.envelope.audio_gains(),
+            _ => [1.0, 1.0],
+        }
+    }
+
+    /// Returns state to be persisted, once there were changes worth saving.
+    pub fn pending_save(&mut self) -> Option<Save> {
+        let sequencer_requested = self.sequencer.take_save_request();
+        if sequencer_requested || self.save_requested {
+            self.save_requested = false;
+            Some(Save {
+                patterns: *self.sequencer.patterns(),
+                clock_speed: self.clock.speed(),
+                calibrations: self.calibrations,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Ask for the state to be persisted again, e.g. when storing of the
+    /// previous save is still in progress.
+    pub fn request_save(&mut self) {
+        self.save_requested = true;
+    }
+
+    /// Returns calibration of CV inputs and outputs, once it got changed.
+    pub fn pending_calibrations(&mut self) -> Option<Calibrations> {
+        if self.calibrations_changed {
+            self.calibrations_changed = false;
+            Some(self.calibrations)
+        } else {
+            None
+        }
+    }
+
+    fn schedule_clock_save(&mut self) {
+        let speed = self.clock.speed();
+        if libm::fabsf(speed - self.saved_clock_speed) > CLOCK_SPEED_THRESHOLD {
+            self.saved_clock_speed = speed;
+            self.save_countdown = SAVE_DELAY;
+        } else if self.save_countdown > 0 {
+            self.save_countdown -= 1;
+            if self.save_countdown == 0 {
+                self.save_requested = true;
+            }
+        }
+    }
+}
+
+impl Mode {
+    fn from_position(bank: u8, switch: u8) -> Self {
+        match (bank, switch) {
+            (0, 1) => Self::Euclidean,
+            (0, 2) => Self::Bernoulli,
+            (0, 3) => Self::Burst,
+            (0, 4) => Self::SyncedLfo,
+            (0, 5) => Self::Lfo,
+            (0, 6) => Self::SampleAndHold,
+            (0, 7) => Self::Quantizer,
+            (1, 0) => Self::Envelope,
+            (1, 1) => Self::Slew,
+            (1, 2) => Self::TuringMachine,
+            (1, 3) => Self::Sequencer,
+            (1, 4) => Self::Logic,
+            (1, 5) => Self::Comparator,
+            (1, 6) => Self::GateUtilities,
+            (1, 7) => Self::SequentialSwitch,
+            (2, 0) => Self::CvMath,
+            (2, 1) => Self::Random,
+            (2, 2) => Self::Chaos,
+            (2, 3) => Self::Recorder,
+            (2, 4) => Self::GateLooper,
+            (2, 5) => Self::Arpeggiator,
+            (2, 6) => Self::Transposer,
+            (2, 7) => Self::Calibrator,
+            _ => Self::Utilities,
+        }
+    }
+}
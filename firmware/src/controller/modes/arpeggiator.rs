@@ -0,0 +1,239 @@
+//! Arpeggiator, playing notes of a chord built on a root note.
+//!
+//! * CV input 1 sets the root note in V/oct, quantized to semitones.
+//! * Pot 1 selects the chord: major, minor, suspended 2nd, suspended 4th,
+//!   dominant 7th, major 7th, minor 7th, diminished or augmented.
+//! * Pot 2 selects the inversion, raising the lowest notes of the chord by
+//!   an octave.
+//! * Pot 3 sets the range of the arpeggio, between 1 and 4 octaves.
+//! * Pot 4 sets gate length, from 10 to 90 % of the clock period.
+//! * Button 1 cycles the pattern: up, down, up and down, random, or as
+//!   played, which goes from the root through the chord as it is spelled.
+//!   The pattern is shown on LED 1 to 4 for a second, all of them lighting
+//!   up for as played.
+//! * Gate input 1 is an external clock. Without it, the arpeggiator follows
+//!   the internal clock.
+//! * Gate input 2 resets the pattern to its start.
+//! * CV output 1 sends the played note in V/oct, CV output 2 the root.
+//! * Gate output 1 sends a gate for each note, gate output 2 a trigger at
+//!   the start of the pattern. Until the period of the external clock gets
+//!   measured, gates are kept short, not to overlap with the next note.
+//! * LED 1 to 4 show which note of the chord is playing.
+//!
+//! Notes that would go above the 5 V range of the output are dropped by
+//! octaves until they fit.
+
+use heapless::Vec;
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+use crate::controller::quantizer::{self, Quantizer, Scale};
+use crate::random_generator::RandomGenerator;
+
+const MAX_OCTAVES: usize = 4;
+const MAX_CHORD: usize = 4;
+const MAX_NOTES: usize = MAX_OCTAVES * MAX_CHORD * 2;
+
+pub struct Arpeggiator {
+    root_input: f32,
+    chord: &'static [i32],
+    inversion: usize,
+    pattern: Pattern,
+    duty: f32,
+    octaves: usize,
+    step: usize,
+    playing: usize,
+    reset: bool,
+    quantizer: Quantizer,
+    indicator: Indicator,
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    reset_detector: EdgeDetector,
+    pattern_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    Up,
+    Down,
+    UpDown,
+    Random,
+    AsPlayed,
+}
+
+impl Arpeggiator {
+    // NOTE: Gate length in ticks used while the clock period is unknown.
+    const SHORT_GATE: usize = 10;
+
+    const CHORDS: [&'static [i32]; 9] = [
+        &[0, 4, 7],
+        &[0, 3, 7],
+        &[0, 2, 7],
+        &[0, 5, 7],
+        &[0, 4, 7, 10],
+        &[0, 4, 7, 11],
+        &[0, 3, 7, 10],
+        &[0, 3, 6],
+        &[0, 4, 8],
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            root_input: 0.0,
+            chord: Self::CHORDS[0],
+            inversion: 0,
+            pattern: Pattern::Up,
+            duty: 0.5,
+            octaves: 1,
+            step: 0,
+            playing: 0,
+            reset: false,
+            quantizer: Quantizer::new(),
+            indicator: Indicator::new(),
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            reset_detector: EdgeDetector::new(),
+            pattern_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.root_input = snapshot.cvs[0].unwrap_or(0.0);
+
+        let chord = (snapshot.pots[0] * (Self::CHORDS.len() as f32 - 0.01)) as usize;
+        self.chord = Self::CHORDS[chord];
+        self.inversion = (snapshot.pots[1] * (self.chord.len() as f32 - 0.01)) as usize;
+        self.octaves = 1 + (snapshot.pots[2] * (MAX_OCTAVES as f32 - 0.01)) as usize;
+        self.duty = 0.1 + snapshot.pots[3] * 0.8;
+
+        if self.pattern_detector.rising(snapshot.buttons[0]) {
+            self.pattern = self.pattern.next();
+            self.indicator.indicate(self.pattern as usize);
+        }
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+        if self.reset_detector.rising(snapshot.gates[1]) {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        clock: &Clock,
+        random_generator: &mut RandomGenerator,
+        outputs: &mut Outputs,
+    ) {
+        let root = self.quantizer.quantize(self.root_input, &Scale::CHROMATIC);
+
+        if self.clock_follower.tick(clock) {
+            let sequence = self.sequence();
+
+            if self.reset {
+                self.reset = false;
+                self.step = 0;
+            } else {
+                self.step = (self.step + 1) % sequence.len();
+            }
+            self.step %= sequence.len();
+
+            let (offset, position) = if self.pattern == Pattern::Random {
+                let index = random_generator.u16().unwrap_or_default() as usize % sequence.len();
+                sequence[index]
+            } else {
+                sequence[self.step]
+            };
+            self.playing = position;
+            outputs.cvs[0].set_value(fit_voct(root + offset));
+
+            let gate_length = if self.clock_follower.is_measured() {
+                let period = self.clock_follower.period(clock);
+                (period * self.duty).min(period - 1.0) as usize
+            } else {
+                Self::SHORT_GATE
+            };
+            outputs.gates[0].enable_with_countdown(gate_length);
+            if self.step == 0 {
+                outputs.gates[1].enable_with_countdown(10);
+            }
+        }
+
+        outputs.cvs[1].set_value(fit_voct(root));
+
+        if !self.indicator.tick(outputs) {
+            for (i, led) in outputs.leds.iter_mut().enumerate() {
+                led.set(i == self.playing);
+            }
+        }
+    }
+
+    /// Build the sequence of notes to play, each as an offset from the root
+    /// in semitones, paired with the position of the note in the chord.
+    fn sequence(&self) -> Vec<(i32, usize), MAX_NOTES> {
+        // NOTE: Inversion raises the lowest notes by an octave, while
+        // keeping the order in which the chord is spelled.
+        let mut chord: Vec<(i32, usize), MAX_CHORD> = Vec::new();
+        for (i, interval) in self.chord.iter().enumerate() {
+            let raised = if i < self.inversion { 12 } else { 0 };
+            let _ = chord.push((interval + raised, i));
+        }
+
+        let mut ascending: Vec<(i32, usize), MAX_NOTES> = Vec::new();
+        for octave in 0..self.octaves as i32 {
+            for (note, position) in chord.iter() {
+                let _ = ascending.push((note + octave * 12, *position));
+            }
+        }
+
+        match self.pattern {
+            Pattern::AsPlayed => ascending,
+            Pattern::Up | Pattern::Random => {
+                ascending.sort_unstable();
+                ascending
+            }
+            Pattern::Down => {
+                ascending.sort_unstable();
+                ascending.reverse();
+                ascending
+            }
+            Pattern::UpDown => {
+                ascending.sort_unstable();
+                let mut sequence = ascending.clone();
+                // NOTE: Skip the top and the bottom note on the way down, so
+                // they don't get repeated.
+                let len = ascending.len();
+                if len > 2 {
+                    for note in ascending[1..len - 1].iter().rev() {
+                        let _ = sequence.push(*note);
+                    }
+                }
+                sequence
+            }
+        }
+    }
+}
+
+impl Pattern {
+    fn next(self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::UpDown,
+            Self::UpDown => Self::Random,
+            Self::Random => Self::AsPlayed,
+            Self::AsPlayed => Self::Up,
+        }
+    }
+}
+
+/// Convert the note to V/oct, dropping octaves until it fits the output.
+fn fit_voct(note: i32) -> f32 {
+    let mut note = note;
+    while note > 60 {
+        note -= 12;
+    }
+    quantizer::note_to_voct(note.max(0))
+}
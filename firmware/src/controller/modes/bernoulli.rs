@@ -0,0 +1,113 @@
+//! Bernoulli gate, routing triggers randomly to one of the gate outputs.
+//!
+//! * Pot 1 sets probability of routing a trigger to the second output.
+//!   CV input 1 is added to it.
+//! * Pot 2 selects the output mode: trigger, toggle or latch. In the trigger
+//!   mode, the chosen output follows the input gate. In the toggle mode,
+//!   the coin toss decides whether to switch to the other output. In the
+//!   latch mode, the chosen output stays high until the other one is chosen.
+//! * Pot 3 sets probability of repeating the last choice regardless of the
+//!   coin toss.
+//! * Gate input 1 and 2 are both accepted as the trigger, button 1 triggers
+//!   manually.
+//! * LED 1 and 2 show which branch fired.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::random_generator::RandomGenerator;
+
+pub struct Bernoulli {
+    probability: f32,
+    repeat_probability: f32,
+    output_mode: OutputMode,
+    input_high: bool,
+    triggered: bool,
+    branch: usize,
+    trigger_detectors: [EdgeDetector; 3],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Trigger,
+    Toggle,
+    Latch,
+}
+
+impl Bernoulli {
+    pub fn new() -> Self {
+        Self {
+            probability: 0.5,
+            repeat_probability: 0.0,
+            output_mode: OutputMode::Trigger,
+            input_high: false,
+            triggered: false,
+            branch: 0,
+            trigger_detectors: [
+                EdgeDetector::new(),
+                EdgeDetector::new(),
+                EdgeDetector::new(),
+            ],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let probability_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        self.probability = (snapshot.pots[0] + probability_cv).clamp(0.0, 1.0);
+        self.output_mode = OutputMode::from_pot(snapshot.pots[1]);
+        self.repeat_probability = snapshot.pots[2];
+
+        let inputs = [snapshot.gates[0], snapshot.gates[1], snapshot.buttons[0]];
+        for (detector, input) in self.trigger_detectors.iter_mut().zip(inputs) {
+            if detector.rising(input) {
+                self.triggered = true;
+            }
+        }
+        self.input_high = inputs.iter().any(|i| *i);
+    }
+
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator, outputs: &mut Outputs) {
+        if self.triggered {
+            self.triggered = false;
+            self.toss(random_generator);
+        }
+
+        let other = 1 - self.branch;
+        match self.output_mode {
+            OutputMode::Trigger | OutputMode::Toggle => {
+                outputs.gates[self.branch].set(self.input_high);
+                outputs.gates[other].set(false);
+            }
+            OutputMode::Latch => {
+                outputs.gates[self.branch].set(true);
+                outputs.gates[other].set(false);
+            }
+        }
+
+        outputs.leds[0].set(outputs.gates[0].value());
+        outputs.leds[1].set(outputs.gates[1].value());
+    }
+
+    fn toss(&mut self, random_generator: &mut RandomGenerator) {
+        if random_generator.f32() < self.repeat_probability {
+            return;
+        }
+
+        let heads = random_generator.f32() < self.probability;
+        self.branch = match self.output_mode {
+            OutputMode::Toggle if heads => 1 - self.branch,
+            OutputMode::Toggle => self.branch,
+            _ => usize::from(heads),
+        };
+    }
+}
+
+impl OutputMode {
+    fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Trigger,
+            1 => Self::Toggle,
+            _ => Self::Latch,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Burst generator, emitting a series of pulses on each trigger.
+//!
+//! * Pot 1 sets the number of pulses in a burst. CV input 1 is added to it.
+//! * Pot 2 sets spacing between pulses, from 10 to 500 ms. When synced to
+//!   the internal clock, it selects a division of the clock period instead.
+//!   CV input 2 is added to it.
+//! * Pot 3 sets the curve of the burst. Turning it left makes the pulses
+//!   decelerate, turning it right accelerate. The center keeps them even.
+//! * Gate input 1 and 2 trigger a burst on gate output 1 and 2.
+//! * Button 1 toggles sync to the internal clock, shown on LED 3.
+//! * LED 1 and 2 follow gate outputs.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::Clock;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::controller::scheduler::PulseScheduler;
+
+const MAX_COUNT: usize = 16;
+
+pub struct Burst {
+    count: usize,
+    spacing: f32,
+    curve: f32,
+    synced: bool,
+    triggered: [bool; 2],
+    schedulers: [PulseScheduler<MAX_COUNT>; 2],
+    trigger_detectors: [EdgeDetector; 2],
+    sync_detector: EdgeDetector,
+}
+
+impl Burst {
+    pub fn new() -> Self {
+        Self {
+            count: 1,
+            spacing: 0.0,
+            curve: 0.0,
+            synced: false,
+            triggered: [false; 2],
+            schedulers: [PulseScheduler::new(), PulseScheduler::new()],
+            trigger_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+            sync_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let count_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        let count = (snapshot.pots[0] + count_cv).clamp(0.0, 1.0);
+        self.count = 1 + (count * (MAX_COUNT as f32 - 0.01)) as usize;
+
+        let spacing_cv = snapshot.cvs[1].unwrap_or(0.0) / 5.0;
+        self.spacing = (snapshot.pots[1] + spacing_cv).clamp(0.0, 1.0);
+
+        // NOTE: Small dead zone around the center to make even spacing easy
+        // to hit.
+        let curve = snapshot.pots[2] * 2.0 - 1.0;
+        self.curve = if libm::fabsf(curve) < 0.05 {
+            0.0
+        } else {
+            curve
+        };
+
+        if self.sync_detector.rising(snapshot.buttons[0]) {
+            self.synced = !self.synced;
+        }
+
+        for (i, detector) in self.trigger_detectors.iter_mut().enumerate() {
+            if detector.rising(snapshot.gates[i]) {
+                self.triggered[i] = true;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        for i in 0..2 {
+            if self.triggered[i] {
+                self.triggered[i] = false;
+                self.schedule_burst(i, clock);
+            }
+            self.schedulers[i].tick(&mut outputs.gates[i]);
+            outputs.leds[i].set(outputs.gates[i].value());
+        }
+        outputs.leds[2].set(self.synced);
+    }
+
+    fn schedule_burst(&mut self, channel: usize, clock: &Clock) {
+        let spacing = self.spacing_in_ticks(clock);
+        let length = (spacing / 2.0).clamp(1.0, 10.0) as usize;
+        let span = spacing * self.count as f32;
+        // NOTE: Exponent below 1.0 shortens the gaps as the burst progresses,
+        // above 1.0 makes them longer.
+        let exponent = libm::powf(4.0, -self.curve);
+
+        let scheduler = &mut self.schedulers[channel];
+        scheduler.clear();
+        let mut previous: Option<usize> = None;
+        for i in 0..self.count {
+            let position = i as f32 / self.count as f32;
+            let mut delay = (span * libm::powf(position, exponent)) as usize;
+            // NOTE: Steep curves would squeeze pulses together until they
+            // merge. Keep at least a tick low between them.
+            if let Some(previous) = previous {
+                delay = delay.max(previous + length + 1);
+            }
+            scheduler.schedule(delay, length);
+            previous = Some(delay);
+        }
+    }
+
+    fn spacing_in_ticks(&self, clock: &Clock) -> f32 {
+        if self.synced {
+            const DIVISIONS: [f32; 8] = [1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0];
+            let index = (self.spacing * (DIVISIONS.len() as f32 - 0.01)) as usize;
+            clock.period() / DIVISIONS[index]
+        } else {
+            const MIN: f32 = 10.0;
+            const MAX: f32 = 500.0;
+            MIN + self.spacing * (MAX - MIN)
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Calibration of CV inputs and outputs, measured per unit.
+//!
+//! The calibration goes through three steps, LED 1 to 3 show the current
+//! one:
+//!
+//! 1. Patch 1 V to all four CV inputs and press button 1.
+//! 2. Patch 3 V to all four CV inputs and press button 1.
+//! 3. Patch CV output 1 to CV input 1 and CV output 2 to CV input 2 and
+//!    press button 1. The outputs send 1 V and then 3 V, measured by the
+//!    freshly calibrated inputs.
+//!
+//! Once done, all LEDs flash and the calibration gets applied and saved. If
+//! any of the measurements is too far off, only LED 4 flashes and the
+//! previous calibration is kept. Button 2 starts over at any time.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::controller::save::{Calibration, Calibrations};
+
+const INPUTS: usize = 4;
+const OUTPUTS: usize = 2;
+
+pub struct Calibrator {
+    // NOTE: Calibration currently applied by the input and output
+    // interfaces. Measurements are taken from the raw values under it.
+    active: Calibrations,
+    step: Step,
+    measurement: Option<Measurement>,
+    inputs: [f32; INPUTS],
+    input_lows: [f32; INPUTS],
+    input_highs: [f32; INPUTS],
+    new_inputs: [Calibration; INPUTS],
+    output_lows: [f32; OUTPUTS],
+    finished: Option<Calibrations>,
+    flash: u32,
+    failed: bool,
+    button_detectors: [EdgeDetector; 2],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    InputLow,
+    InputHigh,
+    OutputLow,
+    OutputHigh,
+}
+
+struct Measurement {
+    settle: u32,
+    remaining: u32,
+    sums: [f32; INPUTS],
+}
+
+impl Calibrator {
+    const LOW: f32 = 1.0;
+    const HIGH: f32 = 3.0;
+    // NOTE: Outputs need some time to get to the new voltage and the input
+    // filters to follow them.
+    const SETTLE_TICKS: u32 = 200;
+    const MEASURE_TICKS: u32 = 200;
+    const FLASH: u32 = 1000;
+
+    pub fn new(calibrations: Calibrations) -> Self {
+        Self {
+            active: calibrations,
+            step: Step::InputLow,
+            measurement: None,
+            inputs: [0.0; INPUTS],
+            input_lows: [0.0; INPUTS],
+            input_highs: [0.0; INPUTS],
+            new_inputs: calibrations.inputs,
+            output_lows: [0.0; OUTPUTS],
+            finished: None,
+            flash: 0,
+            failed: false,
+            button_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+        }
+    }
+
+    /// Returns the new calibration once all the steps are done.
+    pub fn take_calibrations(&mut self) -> Option<Calibrations> {
+        self.finished.take()
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        for (i, input) in self.inputs.iter_mut().enumerate() {
+            let calibration = self.active.inputs[i];
+            // NOTE: Undo the active calibration to get the raw reading.
+            *input = snapshot.cvs[i].unwrap_or(0.0) / calibration.scale - calibration.offset;
+        }
+
+        if self.button_detectors[1].rising(snapshot.buttons[1]) {
+            self.step = Step::InputLow;
+            self.measurement = None;
+        } else if self.button_detectors[0].rising(snapshot.buttons[0])
+            && self.measurement.is_none()
+            && self.step != Step::OutputHigh
+        {
+            self.start_measurement();
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        for (i, cv) in outputs.cvs.iter_mut().enumerate() {
+            let target = match self.step {
+                Step::OutputLow => Self::LOW,
+                Step::OutputHigh => Self::HIGH,
+                _ => 0.0,
+            };
+            // NOTE: Undo the active calibration to send the raw voltage.
+            let calibration = self.active.outputs[i];
+            cv.set_value((target - calibration.offset) / calibration.scale);
+        }
+
+        if let Some(averages) = self.measure() {
+            self.finish_step(averages);
+        }
+
+        if self.flash > 0 {
+            self.flash -= 1;
+            for (i, led) in outputs.leds.iter_mut().enumerate() {
+                led.set(if self.failed { i == 3 } else { true });
+            }
+        } else {
+            let step = match self.step {
+                Step::InputLow => 0,
+                Step::InputHigh => 1,
+                Step::OutputLow | Step::OutputHigh => 2,
+            };
+            for (i, led) in outputs.leds.iter_mut().enumerate() {
+                led.set(i == step);
+            }
+        }
+    }
+
+    fn start_measurement(&mut self) {
+        let settle = match self.step {
+            Step::InputLow | Step::InputHigh => 0,
+            Step::OutputLow | Step::OutputHigh => Self::SETTLE_TICKS,
+        };
+        self.measurement = Some(Measurement {
+            settle,
+            remaining: Self::MEASURE_TICKS,
+            sums: [0.0; INPUTS],
+        });
+    }
+
+    /// Accumulate raw readings, returning their averages once the
+    /// measurement is over.
+    fn measure(&mut self) -> Option<[f32; INPUTS]> {
+        let measurement = self.measurement.as_mut()?;
+        if measurement.settle > 0 {
+            measurement.settle -= 1;
+            return None;
+        }
+
+        for (sum, input) in measurement.sums.iter_mut().zip(self.inputs) {
+            *sum += input;
+        }
+        measurement.remaining -= 1;
+        if measurement.remaining > 0 {
+            return None;
+        }
+
+        let averages = measurement.sums.map(|sum| sum / Self::MEASURE_TICKS as f32);
+        self.measurement = None;
+        Some(averages)
+    }
+
+    fn finish_step(&mut self, averages: [f32; INPUTS]) {
+        match self.step {
+            Step::InputLow => {
+                self.input_lows = averages;
+                self.step = Step::InputHigh;
+            }
+            Step::InputHigh => {
+                self.input_highs = averages;
+                for (i, calibration) in self.new_inputs.iter_mut().enumerate() {
+                    // NOTE: Solving (v + offset) * scale for both references.
+                    let scale =
+                        (Self::HIGH - Self::LOW) / (self.input_highs[i] - self.input_lows[i]);
+                    let offset = Self::LOW / scale - self.input_lows[i];
+                    *calibration = Calibration::new(offset, scale);
+                }
+                self.step = Step::OutputLow;
+            }
+            Step::OutputLow => {
+                self.output_lows = self.read_outputs(averages);
+                self.step = Step::OutputHigh;
+                self.start_measurement();
+            }
+            Step::OutputHigh => {
+                let output_highs = self.read_outputs(averages);
+                let mut calibrations = Calibrations {
+                    inputs: self.new_inputs,
+                    outputs: self.active.outputs,
+                };
+                for (i, calibration) in calibrations.outputs.iter_mut().enumerate() {
+                    // NOTE: Solving v * scale + offset for both references.
+                    let scale = (Self::HIGH - Self::LOW) / (output_highs[i] - self.output_lows[i]);
+                    let offset = Self::LOW - self.output_lows[i] * scale;
+                    *calibration = Calibration::new(offset, scale);
+                }
+
+                let plausible = calibrations
+                    .inputs
+                    .iter()
+                    .chain(calibrations.outputs.iter())
+                    .all(Calibration::is_plausible);
+                if plausible {
+                    self.active = calibrations;
+                    self.finished = Some(calibrations);
+                }
+                self.failed = !plausible;
+                self.flash = Self::FLASH;
+                self.step = Step::InputLow;
+            }
+        }
+    }
+
+    /// Voltage measured on the first two inputs with their new calibration.
+    fn read_outputs(&self, averages: [f32; INPUTS]) -> [f32; OUTPUTS] {
+        let mut voltages = [0.0; OUTPUTS];
+        for (i, voltage) in voltages.iter_mut().enumerate() {
+            let calibration = self.new_inputs[i];
+            *voltage = (averages[i] + calibration.offset) * calibration.scale;
+        }
+        voltages
+    }
+}
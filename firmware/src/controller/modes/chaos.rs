@@ -0,0 +1,94 @@
+//! Chaotic modulation from Lorenz, Rössler or Chua attractor.
+//!
+//! * Pot 1 sets speed, from 0.05 to 20 times the natural speed of the
+//!   system. CV input 1 is added to it.
+//! * Pot 2 sets the amount of chaos. On the left, the system is periodic
+//!   or settles, on the right it is fully chaotic. CV input 2 is added to
+//!   it.
+//! * Pot 3 selects the system: Lorenz, Rössler or Chua.
+//! * Pot 4 sets amplitude of the CV outputs, up to the full 0 to 5 V range
+//!   centered at 2.5 V.
+//! * Gate input 1 resets the system to its initial state.
+//! * CV output 1 and 2 send X and Y of the system.
+//! * Gate output 1 fires when X moves to the positive lobe, gate output 2
+//!   when it moves to the negative one.
+//! * LED 1 and 2 follow gate outputs, LED 3 and 4 light up while X and Y
+//!   are positive.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::attractor::{Attractor, System};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+
+pub struct Chaos {
+    attractor: Attractor,
+    amplitude: f32,
+    positive: bool,
+    reset: bool,
+    reset_detector: EdgeDetector,
+}
+
+impl Chaos {
+    const MIN_SPEED: f32 = 0.05;
+    const MAX_SPEED: f32 = 20.0;
+
+    pub fn new() -> Self {
+        Self {
+            attractor: Attractor::new(),
+            amplitude: 5.0,
+            positive: true,
+            reset: false,
+            reset_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let speed_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        let speed = (snapshot.pots[0] + speed_cv).clamp(0.0, 1.0);
+        self.attractor
+            .set_speed(Self::MIN_SPEED * libm::powf(Self::MAX_SPEED / Self::MIN_SPEED, speed));
+
+        let chaos_cv = snapshot.cvs[1].unwrap_or(0.0) / 5.0;
+        self.attractor
+            .set_chaos((snapshot.pots[1] + chaos_cv).clamp(0.0, 1.0));
+
+        let system = match (snapshot.pots[2] * 2.99) as usize {
+            0 => System::Lorenz,
+            1 => System::Rossler,
+            _ => System::Chua,
+        };
+        self.attractor.set_system(system);
+
+        self.amplitude = snapshot.pots[3] * 5.0;
+
+        if self.reset_detector.rising(snapshot.gates[0]) {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        if self.reset {
+            self.reset = false;
+            self.attractor.reset();
+        }
+
+        self.attractor.tick();
+        let x = self.attractor.x();
+        let y = self.attractor.y();
+
+        let positive = x > 0.0;
+        if positive != self.positive {
+            self.positive = positive;
+            let gate = if positive { 0 } else { 1 };
+            outputs.gates[gate].enable_with_countdown(10);
+        }
+
+        outputs.cvs[0].set_value(2.5 + x * self.amplitude / 2.0);
+        outputs.cvs[1].set_value(2.5 + y * self.amplitude / 2.0);
+
+        outputs.leds[0].set(outputs.gates[0].value());
+        outputs.leds[1].set(outputs.gates[1].value());
+        outputs.leds[2].set(positive);
+        outputs.leds[3].set(y > 0.0);
+    }
+}
@@ -0,0 +1,89 @@
+//! Comparator and window comparator with hysteresis, turning CVs to gates.
+//!
+//! * Pot 1 and 2 set threshold of the first and second channel, between
+//!   -5 and +5 V. When patched, CV input 3 and 4 replace them.
+//! * Pot 3 sets hysteresis, between 0 and 1 V.
+//! * Pot 4 selects between the comparator and the window comparator.
+//! * CV input 1 and 2 are compared, the results are sent to gate output 1
+//!   and 2 and shown on LED 1 and 2.
+//! * LED 3 shows that the window comparator is selected.
+//!
+//! The comparator sets its gate high when the input rises above the
+//! threshold and low when it falls below it, with the hysteresis spread
+//! around the threshold. The window comparator sets its gate high while the
+//! input stays between the two thresholds, whether they come from the pots
+//! or from CV input 3 and 4. Both CV inputs get compared against the same
+//! window.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::output::Outputs;
+use crate::controller::patch_detector::PatchDetector;
+
+pub struct Comparator {
+    window: bool,
+    hysteresis: f32,
+    thresholds: [f32; 2],
+    inputs: [f32; 2],
+    states: [bool; 2],
+    patch_detectors: [PatchDetector; 2],
+}
+
+impl Comparator {
+    const MAX_HYSTERESIS: f32 = 1.0;
+
+    pub fn new() -> Self {
+        Self {
+            window: false,
+            hysteresis: 0.0,
+            thresholds: [0.0, 0.0],
+            inputs: [0.0, 0.0],
+            states: [false, false],
+            patch_detectors: [PatchDetector::new(), PatchDetector::new()],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        for i in 0..2 {
+            let reference = snapshot.cvs[2 + i];
+            self.patch_detectors[i].update(reference);
+            self.thresholds[i] = if self.patch_detectors[i].is_patched() {
+                reference.unwrap_or(0.0)
+            } else {
+                snapshot.pots[i] * 10.0 - 5.0
+            };
+            self.inputs[i] = snapshot.cvs[i].unwrap_or(0.0);
+        }
+        self.hysteresis = snapshot.pots[2] * Self::MAX_HYSTERESIS;
+        self.window = snapshot.pots[3] > 0.5;
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        let margin = self.hysteresis / 2.0;
+
+        for i in 0..2 {
+            let input = self.inputs[i];
+            let state = &mut self.states[i];
+            if self.window {
+                let lower = f32::min(self.thresholds[0], self.thresholds[1]);
+                let upper = f32::max(self.thresholds[0], self.thresholds[1]);
+                if *state {
+                    *state = input > lower - margin && input < upper + margin;
+                } else {
+                    *state = input > lower + margin && input < upper - margin;
+                }
+            } else {
+                let threshold = self.thresholds[i];
+                if *state {
+                    *state = input > threshold - margin;
+                } else {
+                    *state = input > threshold + margin;
+                }
+            }
+
+            outputs.gates[i].set(*state);
+            outputs.leds[i].set(*state);
+        }
+
+        outputs.leds[2].set(self.window);
+    }
+}
@@ -0,0 +1,130 @@
+//! Arithmetic on two CV inputs.
+//!
+//! * CV input 1 and 2 are inputs A and B.
+//! * Pot 1 and 2 select the operation of CV output 1 and 2: minimum,
+//!   maximum, sum, difference, full-wave rectification, half-wave
+//!   rectification or inversion. Unary operations process input A on the
+//!   first output and input B on the second.
+//! * Pot 3 scales results, from 0 to 2 times. The center keeps them
+//!   unchanged.
+//! * Pot 4 sets the center around which signals get rectified and
+//!   inverted, between -5 and +5 V.
+//! * Button 1 cycles how the bipolar results are mapped to the CV outputs
+//!   (see `OutputMapping`). The selected mapping is briefly shown on LED 1
+//!   to 3.
+//! * LED 1 and 2 light up while input A and B are above the center. LED 3
+//!   and 4 light up when CV output 1 and 2 are clipped by the mapping.
+//!
+//! Results are scaled around the center too, so with the center at 0 V,
+//! the difference of A and B is a plain `(A - B) * scale`.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+use crate::controller::output_mapping::OutputMapping;
+
+const MAPPINGS: [OutputMapping; 3] = [
+    OutputMapping::Clip,
+    OutputMapping::Shift,
+    OutputMapping::Scale,
+];
+
+pub struct CvMath {
+    operations: [Operation; 2],
+    scale: f32,
+    center: f32,
+    inputs: [f32; 2],
+    mapping: usize,
+    indicator: Indicator,
+    mapping_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Min,
+    Max,
+    Sum,
+    Difference,
+    FullWave,
+    HalfWave,
+    Invert,
+}
+
+impl CvMath {
+    pub fn new() -> Self {
+        Self {
+            operations: [Operation::Min, Operation::Max],
+            scale: 1.0,
+            center: 0.0,
+            inputs: [0.0, 0.0],
+            mapping: 0,
+            indicator: Indicator::new(),
+            mapping_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.operations = [
+            Operation::from_pot(snapshot.pots[0]),
+            Operation::from_pot(snapshot.pots[1]),
+        ];
+        self.scale = snapshot.pots[2] * 2.0;
+        self.center = snapshot.pots[3] * 10.0 - 5.0;
+        self.inputs = [
+            snapshot.cvs[0].unwrap_or(0.0),
+            snapshot.cvs[1].unwrap_or(0.0),
+        ];
+
+        if self.mapping_detector.rising(snapshot.buttons[0]) {
+            self.mapping = (self.mapping + 1) % MAPPINGS.len();
+            self.indicator.indicate(self.mapping);
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        let [a, b] = self.inputs;
+        let center = self.center;
+        let mapping = MAPPINGS[self.mapping];
+
+        let mut clipped = [false; 2];
+        for (i, clipped) in clipped.iter_mut().enumerate() {
+            let x = self.inputs[i] - center;
+            let result = match self.operations[i] {
+                Operation::Min => f32::min(a, b) - center,
+                Operation::Max => f32::max(a, b) - center,
+                Operation::Sum => a + b - center,
+                Operation::Difference => a - b,
+                Operation::FullWave => libm::fabsf(x),
+                Operation::HalfWave => x.max(0.0),
+                Operation::Invert => -x,
+            };
+            let value = center + result * self.scale;
+            let unclipped = mapping.apply_unclipped(value);
+            *clipped = !(0.0..=5.0).contains(&unclipped);
+            outputs.cvs[i].set_value(mapping.apply(value));
+        }
+
+        if !self.indicator.tick(outputs) {
+            outputs.leds[0].set(a > center);
+            outputs.leds[1].set(b > center);
+            outputs.leds[2].set(clipped[0]);
+            outputs.leds[3].set(clipped[1]);
+        }
+    }
+}
+
+impl Operation {
+    fn from_pot(value: f32) -> Self {
+        const OPERATIONS: [Operation; 7] = [
+            Operation::Min,
+            Operation::Max,
+            Operation::Sum,
+            Operation::Difference,
+            Operation::FullWave,
+            Operation::HalfWave,
+            Operation::Invert,
+        ];
+        OPERATIONS[(value * (OPERATIONS.len() as f32 - 0.01)) as usize]
+    }
+}
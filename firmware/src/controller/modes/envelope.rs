@@ -0,0 +1,128 @@
+//! Two ADSR envelopes triggered by gate inputs.
+//!
+//! * Pot 1 to 4 set attack, decay, sustain and release of both envelopes.
+//!   Attack, decay and release span from 1 ms to 10 s.
+//! * Gate input 1 and 2 trigger the first and second envelope, sent to CV
+//!   output 1 and 2 between 0 and 5 V.
+//! * The envelopes also control gain of the left and right audio channel,
+//!   working as a VCA.
+//! * Button 1 cycles through curve shapes: linear, exponential and
+//!   logarithmic.
+//! * Button 2 cycles through trigger behaviors: retrigger starting the
+//!   attack from zero, legato continuing from the current level, and loop
+//!   cycling attack and decay while the gate is held.
+//! * LED 1 and 2 light up while the first and second envelope is active.
+//!   After a button press, LED 1 to 3 show the selected option for a
+//!   second.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::envelope::{Curve, Envelope as Generator};
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+
+pub struct Envelope {
+    envelopes: [Generator; 2],
+    levels: [f32; 2],
+    curve: Curve,
+    behavior: Behavior,
+    triggered: [bool; 2],
+    gates: [bool; 2],
+    indicator: Indicator,
+    gate_detectors: [EdgeDetector; 2],
+    curve_detector: EdgeDetector,
+    behavior_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Behavior {
+    Retrigger,
+    Legato,
+    Loop,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self {
+            envelopes: [Generator::new(), Generator::new()],
+            levels: [0.0; 2],
+            curve: Curve::Linear,
+            behavior: Behavior::Retrigger,
+            triggered: [false; 2],
+            gates: [false; 2],
+            indicator: Indicator::new(),
+            gate_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+            curve_detector: EdgeDetector::new(),
+            behavior_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let attack = pot_to_time(snapshot.pots[0]);
+        let decay = pot_to_time(snapshot.pots[1]);
+        let sustain = snapshot.pots[2];
+        let release = pot_to_time(snapshot.pots[3]);
+
+        if self.curve_detector.rising(snapshot.buttons[0]) {
+            self.curve = match self.curve {
+                Curve::Linear => Curve::Exponential,
+                Curve::Exponential => Curve::Logarithmic,
+                Curve::Logarithmic => Curve::Linear,
+            };
+            self.indicator.indicate(self.curve as usize);
+        }
+
+        if self.behavior_detector.rising(snapshot.buttons[1]) {
+            self.behavior = match self.behavior {
+                Behavior::Retrigger => Behavior::Legato,
+                Behavior::Legato => Behavior::Loop,
+                Behavior::Loop => Behavior::Retrigger,
+            };
+            self.indicator.indicate(self.behavior as usize);
+        }
+
+        for (i, envelope) in self.envelopes.iter_mut().enumerate() {
+            envelope.set_adsr(attack, decay, sustain, release);
+            envelope.set_curve(self.curve);
+            envelope.set_looping(self.behavior == Behavior::Loop);
+
+            if self.gate_detectors[i].rising(snapshot.gates[i]) {
+                self.triggered[i] = true;
+            }
+            self.gates[i] = snapshot.gates[i];
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        for (i, envelope) in self.envelopes.iter_mut().enumerate() {
+            if self.triggered[i] {
+                self.triggered[i] = false;
+                match self.behavior {
+                    Behavior::Retrigger => envelope.retrigger(),
+                    Behavior::Legato | Behavior::Loop => envelope.trigger(),
+                }
+            }
+            envelope.set_gate(self.gates[i]);
+
+            self.levels[i] = envelope.tick();
+            outputs.cvs[i].set_value(self.levels[i] * 5.0);
+        }
+
+        if !self.indicator.tick(outputs) {
+            outputs.leds[0].set(self.envelopes[0].is_active());
+            outputs.leds[1].set(self.envelopes[1].is_active());
+            outputs.leds[2].set(false);
+            outputs.leds[3].set(false);
+        }
+    }
+
+    /// Gain of the left and right audio channel.
+    pub fn audio_gains(&self) -> [f32; 2] {
+        self.levels
+    }
+}
+
+/// Map pot position to time between 1 ms and 10 s.
+fn pot_to_time(value: f32) -> f32 {
+    0.001 * libm::powf(10_000.0, value)
+}
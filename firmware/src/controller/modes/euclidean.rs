@@ -0,0 +1,127 @@
+//! Euclidean rhythm generator with a pattern per gate output.
+//!
+//! * Pot 1 and 2 set steps and fills of the first pattern.
+//! * Pot 3 and 4 set steps and fills of the second pattern.
+//! * CV input 1 and 2 rotate the first and second pattern.
+//! * CV input 3 and 4 are added to fills of the first and second pattern.
+//! * Button 1 and 2 rotate the first and second pattern by one step.
+//! * Gate input 1 is an external clock. Without it, patterns follow the
+//!   internal clock.
+//! * Gate input 2 resets both patterns to their first step.
+//! * LED 1 and 3 blink on hits of the first and second pattern, LED 2 and 4
+//!   blink when the pattern starts over.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::hysteresis::zone_with_hysteresis;
+use crate::controller::output::Outputs;
+
+pub struct Euclidean {
+    patterns: [Pattern; 2],
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    reset_detector: EdgeDetector,
+    button_detectors: [EdgeDetector; 2],
+}
+
+struct Pattern {
+    steps: usize,
+    fills: usize,
+    rotation: usize,
+    manual_rotation: usize,
+    position: usize,
+    reset: bool,
+}
+
+impl Euclidean {
+    pub fn new() -> Self {
+        Self {
+            patterns: [Pattern::new(), Pattern::new()],
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            reset_detector: EdgeDetector::new(),
+            button_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        for (i, pattern) in self.patterns.iter_mut().enumerate() {
+            pattern.steps = 1 + zone_with_hysteresis(
+                pattern.steps - 1,
+                snapshot.pots[i * 2],
+                Pattern::MAX_STEPS,
+            );
+
+            let fills_cv = snapshot.cvs[2 + i].unwrap_or(0.0) / 5.0;
+            let fills = (snapshot.pots[i * 2 + 1] + fills_cv).clamp(0.0, 1.0);
+            pattern.fills = zone_with_hysteresis(pattern.fills, fills, pattern.steps + 1);
+
+            let rotation = (snapshot.cvs[i].unwrap_or(0.0) / 5.0).clamp(0.0, 1.0);
+            pattern.rotation = zone_with_hysteresis(pattern.rotation, rotation, pattern.steps);
+
+            if self.button_detectors[i].rising(snapshot.buttons[i]) {
+                pattern.manual_rotation = (pattern.manual_rotation + 1) % Pattern::MAX_STEPS;
+            }
+        }
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+
+        if self.reset_detector.rising(snapshot.gates[1]) {
+            self.patterns[0].reset = true;
+            self.patterns[1].reset = true;
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        if !self.clock_follower.tick(clock) {
+            return;
+        }
+
+        for (i, pattern) in self.patterns.iter_mut().enumerate() {
+            pattern.advance();
+            if pattern.is_hit() {
+                outputs.gates[i].enable_with_countdown(10);
+                outputs.leds[i * 2].enable_with_countdown(30);
+            }
+            if pattern.position == 0 {
+                outputs.leds[i * 2 + 1].enable_with_countdown(30);
+            }
+        }
+    }
+}
+
+impl Pattern {
+    const MAX_STEPS: usize = 16;
+
+    fn new() -> Self {
+        Self {
+            steps: 1,
+            fills: 0,
+            rotation: 0,
+            manual_rotation: 0,
+            position: 0,
+            reset: true,
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.reset {
+            self.reset = false;
+            self.position = 0;
+        } else {
+            self.position = (self.position + 1) % self.steps;
+        }
+        // NOTE: The number of steps may have been lowered since the last tick.
+        self.position %= self.steps;
+    }
+
+    fn is_hit(&self) -> bool {
+        // NOTE: Bresenham-like distribution, equivalent to Bjorklund's
+        // algorithm up to rotation.
+        let step = (self.position + self.rotation + self.manual_rotation) % self.steps;
+        (step * self.fills) % self.steps < self.fills
+    }
+}
@@ -0,0 +1,185 @@
+//! Gate looper, recording hits over a clock-synced loop and playing them
+//! back.
+//!
+//! * Pot 1 sets length of the loop, from 1 to 16 beats.
+//! * Pot 2 selects the grid hits get quantized to: off, or 1, 2, 3, 4, 6
+//!   or 8 steps per beat.
+//! * Pot 3 selects the action: play, record or clear. While recording, new
+//!   hits are overdubbed over the loop. While clearing, button 1 and 2
+//!   erase all hits of the first and the second channel instead of
+//!   triggering them.
+//! * Pot 4 sets length of output gates, from 5 to 250 ms.
+//! * Gate input 1 and 2 and button 1 and 2 are hits of the first and
+//!   second channel. Outside of recording, they are passed through. Each
+//!   channel holds up to 64 hits, once it is full, new hits are only
+//!   passed through.
+//! * CV input 1 is an external clock. Without it, the loop follows the
+//!   internal clock.
+//! * Gate output 1 and 2 play the first and second channel.
+//! * CV output 1 sends a ramp from 0 to 5 V over the loop.
+//! * LED 1 and 2 flash with gate outputs, LED 3 lights up while recording
+//!   and LED 4 flashes at the start of the loop.
+//!
+//! Hits are stored as positions in beats, so they keep their place when the
+//! tempo changes. Shortening the loop mutes hits past its end, extending it
+//! again brings them back. Rising edges on gate inputs are latched by an
+//! interrupt, so they are recorded with the precision of a tick.
+
+use heapless::Vec;
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::hysteresis::zone_with_hysteresis;
+use crate::controller::output::Outputs;
+
+const MAX_BEATS: usize = 16;
+const MAX_HITS: usize = 64;
+
+pub struct GateLooper {
+    beats: usize,
+    steps_per_beat: Option<usize>,
+    action: Action,
+    gate_length: usize,
+    beat: usize,
+    position: f32,
+    pending: [bool; 2],
+    channels: [Vec<f32, MAX_HITS>; 2],
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    button_detectors: [EdgeDetector; 2],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Play,
+    Record,
+    Clear,
+}
+
+impl GateLooper {
+    const GRIDS: [Option<usize>; 7] = [None, Some(1), Some(2), Some(3), Some(4), Some(6), Some(8)];
+    // NOTE: Voltage on CV input 1 above which the external clock is high.
+    const CLOCK_THRESHOLD: f32 = 1.0;
+
+    pub fn new() -> Self {
+        Self {
+            beats: 4,
+            steps_per_beat: None,
+            action: Action::Play,
+            gate_length: 10,
+            beat: 0,
+            position: 0.0,
+            pending: [false, false],
+            channels: [Vec::new(), Vec::new()],
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            button_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.beats = 1 + zone_with_hysteresis(self.beats - 1, snapshot.pots[0], MAX_BEATS);
+        let grid = (snapshot.pots[1] * (Self::GRIDS.len() as f32 - 0.01)) as usize;
+        self.steps_per_beat = Self::GRIDS[grid];
+        self.action = Action::from_pot(snapshot.pots[2]);
+        self.gate_length = (5.0 + snapshot.pots[3] * 245.0) as usize;
+
+        for i in 0..2 {
+            let button = self.button_detectors[i].rising(snapshot.buttons[i]);
+            if self.action == Action::Clear && button {
+                self.channels[i].clear();
+            } else if button || snapshot.gate_triggers[i] {
+                self.pending[i] = true;
+            }
+        }
+
+        let clock_high = snapshot.cvs[0].is_some_and(|cv| cv > Self::CLOCK_THRESHOLD);
+        if self.clock_detector.rising(clock_high) {
+            self.clock_follower.trigger();
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        if self.clock_follower.tick(clock) {
+            self.beat = (self.beat + 1) % self.beats;
+            if self.beat == 0 {
+                outputs.leds[3].enable_with_countdown(30);
+            }
+        }
+        // NOTE: The loop may get shortened while playing.
+        self.beat %= self.beats;
+
+        let previous = self.position;
+        self.position = self.beat as f32 + self.clock_follower.phase(clock);
+        let beats = self.beats as f32;
+
+        for i in 0..2 {
+            let mut fire = self.channels[i]
+                .iter()
+                .any(|hit| *hit < beats && crossed(previous, self.position, *hit));
+
+            if self.pending[i] {
+                self.pending[i] = false;
+                match self.action {
+                    Action::Record => {
+                        let hit = self.quantize(self.position);
+                        if self.channels[i].iter().all(|h| *h != hit) {
+                            let _ = self.channels[i].push(hit);
+                        }
+                        // NOTE: Hits quantized ahead get played once the loop
+                        // gets to them, the rest is played right away.
+                        let ahead = wrap(hit - self.position, beats);
+                        if ahead == 0.0 || ahead > beats / 2.0 {
+                            fire = true;
+                        }
+                    }
+                    _ => fire = true,
+                }
+            }
+
+            if fire {
+                outputs.gates[i].enable_with_countdown(self.gate_length);
+                outputs.leds[i].enable_with_countdown(30);
+            }
+        }
+
+        outputs.cvs[0].set_value(self.position / beats * 5.0);
+        outputs.leds[2].set(self.action == Action::Record);
+    }
+
+    fn quantize(&self, position: f32) -> f32 {
+        match self.steps_per_beat {
+            Some(steps_per_beat) => {
+                let steps_per_beat = steps_per_beat as f32;
+                let quantized = libm::roundf(position * steps_per_beat) / steps_per_beat;
+                wrap(quantized, self.beats as f32)
+            }
+            None => position,
+        }
+    }
+}
+
+impl Action {
+    fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Play,
+            1 => Self::Record,
+            _ => Self::Clear,
+        }
+    }
+}
+
+/// Returns true if the hit lies between the previous and the current
+/// position, taking the loop wrapping around into account.
+fn crossed(previous: f32, current: f32, hit: f32) -> bool {
+    if current >= previous {
+        hit > previous && hit <= current
+    } else {
+        hit > previous || hit <= current
+    }
+}
+
+fn wrap(position: f32, length: f32) -> f32 {
+    position - libm::floorf(position / length) * length
+}
@@ -0,0 +1,185 @@
+//! Gate delay, pulse stretcher and gate to trigger converter.
+//!
+//! * Pot 1 and 2 set delay of the first and second channel, from 0 to 2
+//!   seconds. When synced to the internal clock, they select a fraction of
+//!   the clock period instead. CV input 1 and 2 are added to them.
+//! * Pot 3 and 4 set length of the output gate of the first and second
+//!   channel. Fully left, the output follows the length of the input gate.
+//!   Otherwise each rising edge of the input emits a gate from 5 ms,
+//!   serving as a trigger, up to 2 seconds.
+//! * Gate input 1 and 2 are processed into gate output 1 and 2.
+//! * Button 1 toggles sync to the internal clock, shown on LED 3.
+//! * LED 1 and 2 follow gate outputs.
+//!
+//! Up to 16 delayed gates can be pending on each channel. Retriggering a
+//! gate of set length while it is still high extends it. A change of the
+//! length applies to the following gates, only switching between following
+//! the input and a set length drops the pending ones.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::Clock;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::hysteresis::step_with_hysteresis;
+use crate::controller::output::Outputs;
+use crate::controller::scheduler::{EdgeScheduler, PulseScheduler};
+
+const MAX_PENDING: usize = 16;
+
+pub struct GateUtilities {
+    delays: [f32; 2],
+    lengths: [Length; 2],
+    length_steps: [i32; 2],
+    synced: bool,
+    rising: [bool; 2],
+    falling: [bool; 2],
+    rise_delays: [usize; 2],
+    length_changed: [bool; 2],
+    pulse_schedulers: [PulseScheduler<MAX_PENDING>; 2],
+    edge_schedulers: [EdgeScheduler<{ MAX_PENDING * 2 }>; 2],
+    rising_detectors: [EdgeDetector; 2],
+    falling_detectors: [EdgeDetector; 2],
+    sync_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Length {
+    Follow,
+    Fixed(usize),
+}
+
+impl GateUtilities {
+    pub fn new() -> Self {
+        Self {
+            delays: [0.0, 0.0],
+            lengths: [Length::Follow, Length::Follow],
+            length_steps: [0, 0],
+            synced: false,
+            rising: [false, false],
+            falling: [false, false],
+            rise_delays: [0, 0],
+            length_changed: [false, false],
+            pulse_schedulers: [PulseScheduler::new(), PulseScheduler::new()],
+            edge_schedulers: [EdgeScheduler::new(), EdgeScheduler::new()],
+            rising_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+            falling_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+            sync_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        if self.sync_detector.rising(snapshot.buttons[0]) {
+            self.synced = !self.synced;
+        }
+
+        for i in 0..2 {
+            let delay_cv = snapshot.cvs[i].unwrap_or(0.0) / 5.0;
+            self.delays[i] = (snapshot.pots[i] + delay_cv).clamp(0.0, 1.0);
+
+            self.length_steps[i] = step_with_hysteresis(
+                self.length_steps[i],
+                snapshot.pots[2 + i] * Length::STEPS as f32,
+            );
+            let length = Length::from_step(self.length_steps[i]);
+            if core::mem::discriminant(&length) != core::mem::discriminant(&self.lengths[i]) {
+                self.length_changed[i] = true;
+            }
+            self.lengths[i] = length;
+
+            // NOTE: The latched edge is part of the gate level, so even
+            // pulses too short to pass the debouncer are delayed. A pulse
+            // that came and went between two snapshots shows only as the
+            // trigger.
+            let input = snapshot.gates[i];
+            let rising = self.rising_detectors[i].rising(input) || snapshot.gate_triggers[i];
+            if rising {
+                self.rising[i] = true;
+            }
+            if self.falling_detectors[i].rising(!input) || (rising && !input) {
+                self.falling[i] = true;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        for i in 0..2 {
+            let delay = self.delay_in_ticks(i, clock) as usize;
+            let rising = self.rising[i];
+            let falling = self.falling[i];
+            self.rising[i] = false;
+            self.falling[i] = false;
+
+            if self.length_changed[i] {
+                self.length_changed[i] = false;
+                // NOTE: Pending events of the other kind would be left
+                // hanging, drop them and start clean.
+                self.pulse_schedulers[i].clear();
+                self.edge_schedulers[i].clear(&mut outputs.gates[i]);
+            }
+
+            match self.lengths[i] {
+                Length::Follow => {
+                    // NOTE: The end of the gate is delayed as much as its
+                    // start was, so the gate keeps its length even when the
+                    // delay changes in between.
+                    if rising {
+                        self.rise_delays[i] = delay;
+                        self.edge_schedulers[i].schedule(delay, true);
+                    }
+                    if falling {
+                        self.edge_schedulers[i].schedule(self.rise_delays[i], false);
+                    }
+                    self.edge_schedulers[i].tick(&mut outputs.gates[i]);
+                }
+                Length::Fixed(length) => {
+                    if rising {
+                        self.pulse_schedulers[i].schedule(delay, length);
+                    }
+                    self.pulse_schedulers[i].tick(&mut outputs.gates[i]);
+                }
+            }
+
+            outputs.leds[i].set(outputs.gates[i].value());
+        }
+        outputs.leds[2].set(self.synced);
+    }
+
+    fn delay_in_ticks(&self, channel: usize, clock: &Clock) -> f32 {
+        let delay = self.delays[channel];
+        if self.synced {
+            const FRACTIONS: [f32; 8] = [
+                0.0,
+                1.0 / 8.0,
+                1.0 / 4.0,
+                1.0 / 3.0,
+                1.0 / 2.0,
+                2.0 / 3.0,
+                3.0 / 4.0,
+                1.0,
+            ];
+            let index = (delay * (FRACTIONS.len() as f32 - 0.01)) as usize;
+            clock.period() * FRACTIONS[index]
+        } else {
+            const MAX: f32 = 2000.0;
+            delay * MAX
+        }
+    }
+}
+
+impl Length {
+    // NOTE: The pot is split into steps of about 3 % of the length, so
+    // the length does not jitter with the pot.
+    const STEPS: i32 = 200;
+    const FOLLOW_STEPS: i32 = 10;
+
+    fn from_step(step: i32) -> Self {
+        const MIN: f32 = 5.0;
+        const MAX: f32 = 2000.0;
+        if step < Self::FOLLOW_STEPS {
+            Self::Follow
+        } else {
+            let position =
+                (step - Self::FOLLOW_STEPS) as f32 / (Self::STEPS - Self::FOLLOW_STEPS) as f32;
+            Self::Fixed((MIN * libm::powf(MAX / MIN, position)) as usize)
+        }
+    }
+}
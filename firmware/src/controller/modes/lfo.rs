@@ -0,0 +1,123 @@
+//! Free running LFO on each CV output.
+//!
+//! Button 1 toggles which of the two LFOs is controlled by the pots, LED 3
+//! and 4 show the selection. After the toggle, pots need to be moved to
+//! take over the newly selected LFO.
+//!
+//! * Pot 1 sets rate, between 0.02 and 20 Hz.
+//! * Pot 2 selects the waveshape: sine, triangle, ramp up, ramp down,
+//!   square, stepped random or smooth random.
+//! * Pot 3 sets amplitude, between 0 and 5 V.
+//! * Pot 4 sets offset, between 0 and 5 V.
+//! * CV input 1 and 2 control rate of the first and second LFO in V/oct.
+//! * CV input 3 is added to the amplitude and CV input 4 to the offset of
+//!   both LFOs. The amplitude gets limited so the waveform never goes
+//!   above 5 V.
+//! * Gate output 1 and 2 and LED 1 and 2 signal start of each cycle.
+//!
+//! While button 2 is held, pot 2 sets pulse width of the square of the
+//! selected LFO. After the button is released, the pot needs to be moved
+//! to select the waveshape again.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::lfo::{Lfo, Waveform};
+use crate::controller::output::Outputs;
+use crate::controller::pot_latch::PotLatch;
+use crate::random_generator::RandomGenerator;
+
+pub struct LfoMode {
+    lfos: [Lfo; 2],
+    rates: [f32; 2],
+    amplitudes: [f32; 2],
+    offsets: [f32; 2],
+    pulse_widths: [f32; 2],
+    pulse_width_layer: bool,
+    selected: usize,
+    select_detector: EdgeDetector,
+    latches: [PotLatch; 4],
+}
+
+impl LfoMode {
+    pub fn new() -> Self {
+        Self {
+            lfos: [Lfo::new(), Lfo::new()],
+            rates: [1.0, 1.0],
+            amplitudes: [0.0, 0.0],
+            offsets: [0.0, 0.0],
+            pulse_widths: [0.5, 0.5],
+            pulse_width_layer: false,
+            selected: 0,
+            select_detector: EdgeDetector::new(),
+            latches: [
+                PotLatch::new(),
+                PotLatch::new(),
+                PotLatch::new(),
+                PotLatch::new(),
+            ],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        if self.select_detector.rising(snapshot.buttons[0]) {
+            self.selected = 1 - self.selected;
+            for (latch, pot) in self.latches.iter_mut().zip(snapshot.pots) {
+                latch.release(pot);
+            }
+        }
+
+        let pulse_width_layer = snapshot.buttons[1];
+        if pulse_width_layer != self.pulse_width_layer {
+            self.pulse_width_layer = pulse_width_layer;
+            self.latches[1].release(snapshot.pots[1]);
+        }
+
+        let selected = self.selected;
+        if let Some(value) = self.latches[0].update(snapshot.pots[0]) {
+            const MIN: f32 = 0.02;
+            const RANGE: f32 = 1000.0;
+            self.rates[selected] = MIN * libm::powf(RANGE, value);
+        }
+        if let Some(value) = self.latches[1].update(snapshot.pots[1]) {
+            if self.pulse_width_layer {
+                self.pulse_widths[selected] = value.clamp(0.01, 0.99);
+            } else {
+                self.lfos[selected].set_waveform(Waveform::from_pot(value));
+            }
+        }
+        if let Some(value) = self.latches[2].update(snapshot.pots[2]) {
+            self.amplitudes[selected] = value * 5.0;
+        }
+        if let Some(value) = self.latches[3].update(snapshot.pots[3]) {
+            self.offsets[selected] = value * 5.0;
+        }
+
+        let amplitude_cv = snapshot.cvs[2].unwrap_or(0.0);
+        let offset_cv = snapshot.cvs[3].unwrap_or(0.0);
+        for (i, lfo) in self.lfos.iter_mut().enumerate() {
+            let voct = snapshot.cvs[i].unwrap_or(0.0);
+            let frequency = self.rates[i] * libm::exp2f(voct);
+            // NOTE: Keep well below the Nyquist of the control rate.
+            lfo.set_frequency(frequency.min(50.0));
+            lfo.set_pulse_width(self.pulse_widths[i]);
+
+            let offset = (self.offsets[i] + offset_cv).clamp(0.0, 5.0);
+            // NOTE: Only the headroom above the offset is available.
+            let amplitude = (self.amplitudes[i] + amplitude_cv).clamp(0.0, 5.0 - offset);
+            lfo.set_offset(offset);
+            lfo.set_amplitude(amplitude);
+        }
+    }
+
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator, outputs: &mut Outputs) {
+        for (i, lfo) in self.lfos.iter_mut().enumerate() {
+            if lfo.tick(random_generator) {
+                outputs.gates[i].enable_with_countdown(10);
+                outputs.leds[i].enable_with_countdown(30);
+            }
+            lfo.write(&mut outputs.cvs[i]);
+        }
+        outputs.leds[2].set(self.selected == 0);
+        outputs.leds[3].set(self.selected == 1);
+    }
+}
@@ -0,0 +1,130 @@
+//! Boolean logic and flip-flops combining two gate signals.
+//!
+//! * Input A is gate input 1, CV input 1 above its threshold or button 1.
+//!   Input B is gate input 2, CV input 2 above its threshold or button 2.
+//! * Pot 1 and 2 select the function of gate output 1 and 2: AND, OR, XOR,
+//!   NAND, NOR, XNOR, T flip-flop, D flip-flop and toggle.
+//! * Pot 3 and 4 set threshold of CV input 1 and 2, between 0 and 5 V.
+//! * LED 1 and 2 show inputs A and B, LED 3 and 4 show gate outputs 1 and 2.
+//!
+//! Flip-flops are clocked by the rising edge of input A. The T flip-flop
+//! flips its state while input B is high, the D flip-flop takes the value
+//! of input B. The toggle flips its state on every rising edge of A.
+//!
+//! Rising edges on gate inputs are latched by an interrupt. Even pulses
+//! shorter than the 1 ms control period are therefore never missed, they
+//! show on the output as a pulse of a few milliseconds.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+
+pub struct Logic {
+    functions: [Function; 2],
+    inputs: [bool; 2],
+    clocked: bool,
+    states: [bool; 2],
+    edge_detectors: [EdgeDetector; 2],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Function {
+    And,
+    Or,
+    Xor,
+    Nand,
+    Nor,
+    Xnor,
+    TFlipFlop,
+    DFlipFlop,
+    Toggle,
+}
+
+impl Logic {
+    pub fn new() -> Self {
+        Self {
+            functions: [Function::And, Function::Or],
+            inputs: [false, false],
+            clocked: false,
+            states: [false, false],
+            edge_detectors: [EdgeDetector::new(), EdgeDetector::new()],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.functions = [
+            Function::from_pot(snapshot.pots[0]),
+            Function::from_pot(snapshot.pots[1]),
+        ];
+
+        for i in 0..2 {
+            let threshold = snapshot.pots[2 + i] * 5.0;
+            let cv_high = snapshot.cvs[i].is_some_and(|cv| cv > threshold);
+            let other_high = cv_high || snapshot.buttons[i];
+            let other_rising = self.edge_detectors[i].rising(other_high);
+
+            self.inputs[i] = snapshot.gates[i] || other_high;
+
+            if i == 0 && (snapshot.gate_triggers[0] || other_rising) {
+                self.clocked = true;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        let [a, b] = self.inputs;
+        let clocked = self.clocked;
+        self.clocked = false;
+
+        for i in 0..2 {
+            let value = match self.functions[i] {
+                Function::And => a && b,
+                Function::Or => a || b,
+                Function::Xor => a != b,
+                Function::Nand => !(a && b),
+                Function::Nor => !(a || b),
+                Function::Xnor => a == b,
+                Function::TFlipFlop => {
+                    if clocked && b {
+                        self.states[i] = !self.states[i];
+                    }
+                    self.states[i]
+                }
+                Function::DFlipFlop => {
+                    if clocked {
+                        self.states[i] = b;
+                    }
+                    self.states[i]
+                }
+                Function::Toggle => {
+                    if clocked {
+                        self.states[i] = !self.states[i];
+                    }
+                    self.states[i]
+                }
+            };
+            outputs.gates[i].set(value);
+            outputs.leds[2 + i].set(value);
+        }
+
+        outputs.leds[0].set(a);
+        outputs.leds[1].set(b);
+    }
+}
+
+impl Function {
+    fn from_pot(value: f32) -> Self {
+        const FUNCTIONS: [Function; 9] = [
+            Function::And,
+            Function::Or,
+            Function::Xor,
+            Function::Nand,
+            Function::Nor,
+            Function::Xnor,
+            Function::TFlipFlop,
+            Function::DFlipFlop,
+            Function::Toggle,
+        ];
+        FUNCTIONS[(value * (FUNCTIONS.len() as f32 - 0.01)) as usize]
+    }
+}
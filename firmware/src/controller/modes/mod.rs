@@ -0,0 +1,24 @@
+pub mod arpeggiator;
+pub mod bernoulli;
+pub mod burst;
+pub mod calibrator;
+pub mod chaos;
+pub mod comparator;
+pub mod cv_math;
+pub mod envelope;
+pub mod euclidean;
+pub mod gate_looper;
+pub mod gate_utilities;
+pub mod lfo;
+pub mod logic;
+pub mod quantizer;
+pub mod random;
+pub mod recorder;
+pub mod sample_and_hold;
+pub mod sequencer;
+pub mod sequential_switch;
+pub mod slew;
+pub mod synced_lfo;
+pub mod transposer;
+pub mod turing_machine;
+pub mod utilities;
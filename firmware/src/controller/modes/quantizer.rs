@@ -0,0 +1,120 @@
+//! Two channels of pitch quantizer.
+//!
+//! * CV input 1 and 2 are quantized and sent to CV output 1 and 2 in V/oct.
+//! * Pot 1 selects the scale: chromatic, major, minor, major pentatonic,
+//!   minor pentatonic, dorian, phrygian, lydian, mixolydian, locrian or
+//!   user-defined.
+//! * Pot 2 and 3 transpose the first and second channel by -12 to +12
+//!   semitones, shifting the key.
+//! * Gate output 1 and 2 and LED 1 and 2 fire when the note of the first
+//!   and second channel changes.
+//!
+//! The user-defined scale is programmed by playing notes to CV input 1.
+//! Button 2 toggles the note at the input in or out of the scale, LED 3
+//! lights up when it is part of the scale. Button 1 clears the scale. An
+//! empty scale behaves as chromatic.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::hysteresis::step_with_hysteresis;
+use crate::controller::output::Outputs;
+use crate::controller::quantizer::{self, Quantizer as NoteQuantizer, Scale};
+
+pub struct Quantizer {
+    scale: ScaleSelection,
+    user_scale: Scale,
+    channels: [Channel; 2],
+    clear_detector: EdgeDetector,
+    toggle_detector: EdgeDetector,
+}
+
+struct Channel {
+    input: f32,
+    transposition: i32,
+    note: Option<i32>,
+    quantizer: NoteQuantizer,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScaleSelection {
+    Preset(Scale),
+    User,
+}
+
+impl Quantizer {
+    const PRESETS: [Scale; 10] = [
+        Scale::CHROMATIC,
+        Scale::MAJOR,
+        Scale::MINOR,
+        Scale::MAJOR_PENTATONIC,
+        Scale::MINOR_PENTATONIC,
+        Scale::DORIAN,
+        Scale::PHRYGIAN,
+        Scale::LYDIAN,
+        Scale::MIXOLYDIAN,
+        Scale::LOCRIAN,
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            scale: ScaleSelection::Preset(Scale::CHROMATIC),
+            user_scale: Scale::empty(),
+            channels: [Channel::new(), Channel::new()],
+            clear_detector: EdgeDetector::new(),
+            toggle_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let index = (snapshot.pots[0] * (Self::PRESETS.len() as f32 + 0.99)) as usize;
+        self.scale = match Self::PRESETS.get(index) {
+            Some(scale) => ScaleSelection::Preset(*scale),
+            None => ScaleSelection::User,
+        };
+
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            channel.input = snapshot.cvs[i].unwrap_or(0.0);
+            channel.transposition =
+                step_with_hysteresis(channel.transposition, snapshot.pots[1 + i] * 24.0 - 12.0);
+        }
+
+        if self.clear_detector.rising(snapshot.buttons[0]) {
+            self.user_scale = Scale::empty();
+        }
+        if self.toggle_detector.rising(snapshot.buttons[1]) {
+            self.user_scale
+                .toggle(libm::roundf(self.channels[0].input * 12.0) as i32);
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        let scale = match self.scale {
+            ScaleSelection::Preset(scale) => scale,
+            ScaleSelection::User => self.user_scale,
+        };
+
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            let note = channel.quantizer.quantize(channel.input, &scale) + channel.transposition;
+            if channel.note != Some(note) {
+                channel.note = Some(note);
+                outputs.gates[i].enable_with_countdown(10);
+                outputs.leds[i].enable_with_countdown(30);
+            }
+            outputs.cvs[i].set_value(quantizer::note_to_voct(note));
+        }
+
+        let input_note = libm::roundf(self.channels[0].input * 12.0) as i32;
+        outputs.leds[2].set(self.user_scale.contains(input_note));
+    }
+}
+
+impl Channel {
+    fn new() -> Self {
+        Self {
+            input: 0.0,
+            transposition: 0,
+            note: None,
+            quantizer: NoteQuantizer::new(),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Random voltage source with selectable distribution.
+//!
+//! On each clock, a new random value is drawn and sent to the CV outputs.
+//!
+//! * Pot 1 selects the distribution: uniform, Gaussian, exponential, or
+//!   biased towards high values.
+//! * Pot 2 sets spread of the values, from 0 to 5 V. CV input 1 is added
+//!   to it.
+//! * Pot 3 sets correlation with the previous value. On the left, each
+//!   value is fully new. Turning it right blends the new value with the
+//!   previous one, down to a slow random walk. CV input 2 is added to it.
+//! * Pot 4 sets slew time of the second output, up to 2 seconds.
+//! * Gate input 1 is an external clock. Without it, the source follows the
+//!   internal clock.
+//! * Button 1 cycles quantization of the outputs: none, chromatic, major
+//!   or minor pentatonic, shown on LEDs for a second.
+//! * CV output 1 sends the stepped value, CV output 2 its slewed variant.
+//! * Gate output 1 sends a trigger on each new value, gate output 2 when
+//!   the new value is higher than the previous one.
+//! * LED 1 and 2 follow gate outputs.
+
+use core::f32::consts::PI;
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+use crate::controller::quantizer::{self, Quantizer, Scale};
+use crate::controller::slew::Slew;
+use crate::random_generator::RandomGenerator;
+
+pub struct Random {
+    distribution: Distribution,
+    spread: f32,
+    correlation: f32,
+    value: f32,
+    scale: usize,
+    quantizer: Quantizer,
+    slew: Slew,
+    indicator: Indicator,
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    scale_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Distribution {
+    Uniform,
+    Gaussian,
+    Exponential,
+    Biased,
+}
+
+impl Random {
+    const SCALES: [Option<Scale>; 4] = [
+        None,
+        Some(Scale::CHROMATIC),
+        Some(Scale::MAJOR),
+        Some(Scale::MINOR_PENTATONIC),
+    ];
+    const MAX_SLEW: f32 = 2.0;
+
+    pub fn new() -> Self {
+        Self {
+            distribution: Distribution::Uniform,
+            spread: 5.0,
+            correlation: 0.0,
+            value: 0.0,
+            scale: 0,
+            quantizer: Quantizer::new(),
+            slew: Slew::new(),
+            indicator: Indicator::new(),
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            scale_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.distribution = Distribution::from_pot(snapshot.pots[0]);
+
+        let spread_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        self.spread = (snapshot.pots[1] + spread_cv).clamp(0.0, 1.0) * 5.0;
+
+        let correlation_cv = snapshot.cvs[1].unwrap_or(0.0) / 5.0;
+        self.correlation = (snapshot.pots[2] + correlation_cv).clamp(0.0, 1.0);
+
+        let slew = snapshot.pots[3] * Self::MAX_SLEW;
+        self.slew.set_times(slew, slew);
+
+        if self.scale_detector.rising(snapshot.buttons[0]) {
+            self.scale = (self.scale + 1) % Self::SCALES.len();
+            self.indicator.indicate(self.scale);
+        }
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        clock: &Clock,
+        random_generator: &mut RandomGenerator,
+        outputs: &mut Outputs,
+    ) {
+        if self.clock_follower.tick(clock) {
+            let drawn = self.distribution.draw(random_generator);
+            // NOTE: Correlation of 1.0 would freeze the value, keep at least
+            // a small step to get a random walk.
+            let step = 1.0 - self.correlation * 0.98;
+            let value = self.value + (drawn - self.value) * step;
+
+            outputs.gates[0].enable_with_countdown(10);
+            if value > self.value {
+                outputs.gates[1].enable_with_countdown(10);
+            }
+            self.value = value;
+        }
+
+        let voltage = self.value * self.spread;
+        let voltage = match &Self::SCALES[self.scale] {
+            Some(scale) => quantizer::note_to_voct(self.quantizer.quantize(voltage, scale)),
+            None => voltage,
+        };
+        self.slew.tick(voltage);
+        outputs.cvs[0].set_value(voltage);
+        outputs.cvs[1].set_value(self.slew.value());
+
+        if !self.indicator.tick(outputs) {
+            outputs.leds[0].set(outputs.gates[0].value());
+            outputs.leds[1].set(outputs.gates[1].value());
+        }
+    }
+}
+
+impl Distribution {
+    fn from_pot(value: f32) -> Self {
+        match (value * 3.99) as usize {
+            0 => Self::Uniform,
+            1 => Self::Gaussian,
+            2 => Self::Exponential,
+            _ => Self::Biased,
+        }
+    }
+
+    /// Draw a value between 0.0 and 1.0.
+    fn draw(self, random_generator: &mut RandomGenerator) -> f32 {
+        let uniform = random_generator.f32();
+        let value = match self {
+            Self::Uniform => uniform,
+            Self::Gaussian => {
+                // NOTE: Box-Muller transform, with standard deviation scaled
+                // so nearly all values fit in the range.
+                let other = random_generator.f32();
+                let radius = libm::sqrtf(-2.0 * libm::logf(uniform.max(f32::EPSILON)));
+                0.5 + radius * libm::cosf(2.0 * PI * other) / 6.0
+            }
+            Self::Exponential => {
+                const RATE: f32 = 4.0;
+                -libm::logf((1.0 - uniform).max(f32::EPSILON)) / RATE
+            }
+            Self::Biased => libm::sqrtf(uniform),
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,225 @@
+//! CV recorder, looping recorded gestures of a pot or a CV input.
+//!
+//! * Pot 1 is recorded. When CV input 1 is patched, it is recorded instead.
+//! * Pot 2 sets playback speed, from a quarter to four times the recorded
+//!   speed. The center locks to the original speed.
+//! * Pot 3 sets how much of the input is mixed into the loop while
+//!   overdubbing. Fully right replaces the loop.
+//! * Pot 4 turned right syncs start and end of the recording to the clock.
+//! * Button 1 starts recording a new loop, pressing it again stops the
+//!   recording and starts playback. Once there is a loop, button 1 toggles
+//!   overdubbing.
+//! * Button 2 clears the loop.
+//! * Gate input 1 is an external clock. Without it, the recording syncs to
+//!   the internal clock.
+//! * Gate input 2 resets playback to the start of the loop.
+//! * CV output 1 plays the loop, CV output 2 passes the recorded input
+//!   through.
+//! * Gate output 1 sends a trigger at the start of each loop.
+//! * LED 1 lights up while recording, LED 2 while overdubbing, LED 3 flashes
+//!   at the start of each loop and LED 4 shows the sync.
+//!
+//! The loop is recorded at the control rate into the reserved SRAM, which
+//! fits over a minute and a half of recording.
+
+use core::mem::MaybeUninit;
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::controller::patch_detector::PatchDetector;
+
+pub struct Recorder {
+    memory: &'static mut [u32],
+    state: State,
+    length: usize,
+    position: f32,
+    input: f32,
+    speed: f32,
+    mix: f32,
+    synced: bool,
+    toggled: bool,
+    cleared: bool,
+    reset: bool,
+    patch_detector: PatchDetector,
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    record_detector: EdgeDetector,
+    clear_detector: EdgeDetector,
+    reset_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Empty,
+    Armed,
+    Recording,
+    Stopping,
+    Playing,
+    Overdubbing,
+}
+
+impl Recorder {
+    pub fn new(memory: &'static mut [MaybeUninit<u32>]) -> Self {
+        for cell in memory.iter_mut() {
+            cell.write(0);
+        }
+        // SAFETY: All the cells were initialized above, and `MaybeUninit<u32>`
+        // has the same layout as `u32`.
+        let memory = unsafe { &mut *(memory as *mut [MaybeUninit<u32>] as *mut [u32]) };
+
+        Self {
+            memory,
+            state: State::Empty,
+            length: 0,
+            position: 0.0,
+            input: 0.0,
+            speed: 1.0,
+            mix: 1.0,
+            synced: false,
+            toggled: false,
+            cleared: false,
+            reset: false,
+            patch_detector: PatchDetector::new(),
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            record_detector: EdgeDetector::new(),
+            clear_detector: EdgeDetector::new(),
+            reset_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.patch_detector.update(snapshot.cvs[0]);
+        self.input = if self.patch_detector.is_patched() {
+            snapshot.cvs[0].unwrap_or(0.0)
+        } else {
+            snapshot.pots[0] * 5.0
+        };
+
+        // NOTE: Small dead zone around the center to make the original speed
+        // easy to hit.
+        let speed = snapshot.pots[1] * 2.0 - 1.0;
+        let speed = if libm::fabsf(speed) < 0.05 {
+            0.0
+        } else {
+            speed
+        };
+        self.speed = libm::powf(4.0, speed);
+
+        self.mix = snapshot.pots[2];
+        self.synced = snapshot.pots[3] > 0.5;
+
+        if self.record_detector.rising(snapshot.buttons[0]) {
+            self.toggled = true;
+        }
+        if self.clear_detector.rising(snapshot.buttons[1]) {
+            self.cleared = true;
+        }
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+        if self.reset_detector.rising(snapshot.gates[1]) {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        let beat = self.clock_follower.tick(clock);
+
+        if self.cleared {
+            self.cleared = false;
+            self.state = State::Empty;
+            self.length = 0;
+        }
+
+        if self.toggled {
+            self.toggled = false;
+            self.state = match self.state {
+                State::Empty if self.synced => State::Armed,
+                State::Empty => self.start_recording(),
+                State::Armed => State::Empty,
+                State::Recording if self.synced => State::Stopping,
+                State::Recording | State::Stopping => self.start_playback(),
+                State::Playing => State::Overdubbing,
+                State::Overdubbing => State::Playing,
+            };
+        }
+
+        if beat {
+            self.state = match self.state {
+                State::Armed => self.start_recording(),
+                State::Stopping => self.start_playback(),
+                state => state,
+            };
+        }
+
+        if self.reset {
+            self.reset = false;
+            self.position = 0.0;
+        }
+
+        match self.state {
+            State::Empty | State::Armed => {
+                outputs.cvs[0].set_value(self.input);
+            }
+            State::Recording | State::Stopping => {
+                self.memory[self.length] = self.input.to_bits();
+                self.length += 1;
+                if self.length == self.memory.len() {
+                    self.state = self.start_playback();
+                }
+                outputs.cvs[0].set_value(self.input);
+            }
+            State::Playing | State::Overdubbing => {
+                if self.state == State::Overdubbing {
+                    let index = self.position as usize;
+                    let recorded = f32::from_bits(self.memory[index]);
+                    let mixed = recorded + (self.input - recorded) * self.mix;
+                    self.memory[index] = mixed.to_bits();
+                }
+
+                outputs.cvs[0].set_value(self.play());
+
+                self.position += self.speed;
+                if self.position >= self.length as f32 {
+                    self.position = libm::fmodf(self.position, self.length as f32);
+                    outputs.gates[0].enable_with_countdown(10);
+                    outputs.leds[2].enable_with_countdown(30);
+                }
+            }
+        }
+
+        outputs.cvs[1].set_value(self.input);
+
+        outputs.leds[0].set(matches!(self.state, State::Recording | State::Stopping));
+        outputs.leds[1].set(self.state == State::Overdubbing);
+        outputs.leds[3].set(self.synced);
+    }
+
+    fn start_recording(&mut self) -> State {
+        self.length = 0;
+        State::Recording
+    }
+
+    fn start_playback(&mut self) -> State {
+        self.position = 0.0;
+        if self.length == 0 {
+            State::Empty
+        } else {
+            State::Playing
+        }
+    }
+
+    /// Read the loop at the current position, interpolating between
+    /// recorded samples.
+    fn play(&self) -> f32 {
+        let index = self.position as usize;
+        let next = (index + 1) % self.length;
+        let fraction = self.position - index as f32;
+        let a = f32::from_bits(self.memory[index]);
+        let b = f32::from_bits(self.memory[next]);
+        a + (b - a) * fraction
+    }
+}
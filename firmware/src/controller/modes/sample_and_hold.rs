@@ -0,0 +1,118 @@
+//! Two channels of sample and hold or track and hold.
+//!
+//! * Gate input 1 and 2 clock the first and second channel.
+//! * CV input 1 and 2 are sampled by the first and second channel and sent
+//!   to CV output 1 and 2. When a CV input is not patched, internal noise
+//!   is sampled instead.
+//! * Pot 1 and 2 set level of the first and second channel.
+//! * Pot 3 selects how the bipolar signal is mapped to the CV outputs (see
+//!   `OutputMapping`).
+//! * Button 1 and 2 toggle between sample and hold and track and hold of
+//!   the first and second channel. LED 3 and 4 light up on track and hold.
+//!   In the track and hold mode, the output follows the input while the
+//!   gate is high.
+//! * Gate output 1 and 2 and LED 1 and 2 signal when a new value was held.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::controller::output_mapping::OutputMapping;
+use crate::controller::patch_detector::PatchDetector;
+use crate::random_generator::RandomGenerator;
+
+pub struct SampleAndHold {
+    channels: [Channel; 2],
+    output_mapping: OutputMapping,
+}
+
+struct Channel {
+    input: f32,
+    patched: PatchDetector,
+    level: f32,
+    gate: bool,
+    tracking: bool,
+    held: f32,
+    triggered: bool,
+    released: bool,
+    gate_detector: EdgeDetector,
+    mode_detector: EdgeDetector,
+}
+
+impl SampleAndHold {
+    pub fn new() -> Self {
+        Self {
+            channels: [Channel::new(), Channel::new()],
+            output_mapping: OutputMapping::Clip,
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            channel.patched.update(snapshot.cvs[i]);
+            channel.input = snapshot.cvs[i].unwrap_or(0.0);
+            channel.level = snapshot.pots[i];
+
+            if channel.mode_detector.rising(snapshot.buttons[i]) {
+                channel.tracking = !channel.tracking;
+            }
+
+            let gate = snapshot.gates[i];
+            if channel.gate_detector.rising(gate) {
+                channel.triggered = true;
+            }
+            if channel.gate && !gate {
+                channel.released = true;
+            }
+            channel.gate = gate;
+        }
+
+        self.output_mapping = OutputMapping::from_pot(snapshot.pots[2]);
+    }
+
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator, outputs: &mut Outputs) {
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            let source = if channel.patched.is_patched() {
+                channel.input
+            } else {
+                random_generator.f32() * 10.0 - 5.0
+            };
+
+            if channel.tracking {
+                if channel.gate {
+                    channel.held = source;
+                }
+                if channel.released {
+                    outputs.gates[i].enable_with_countdown(10);
+                }
+                outputs.leds[i].set(channel.gate);
+            } else if channel.triggered {
+                channel.held = source;
+                outputs.gates[i].enable_with_countdown(10);
+                outputs.leds[i].enable_with_countdown(30);
+            }
+            channel.triggered = false;
+            channel.released = false;
+
+            outputs.leds[2 + i].set(channel.tracking);
+            let value = channel.held * channel.level;
+            outputs.cvs[i].set_value(self.output_mapping.apply(value));
+        }
+    }
+}
+
+impl Channel {
+    fn new() -> Self {
+        Self {
+            input: 0.0,
+            patched: PatchDetector::new(),
+            level: 1.0,
+            gate: false,
+            tracking: false,
+            held: 0.0,
+            triggered: false,
+            released: false,
+            gate_detector: EdgeDetector::new(),
+            mode_detector: EdgeDetector::new(),
+        }
+    }
+}
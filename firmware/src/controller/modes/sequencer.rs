@@ -0,0 +1,230 @@
+//! Step sequencer with up to 16 steps and two lanes, stored persistently.
+//!
+//! * Button 1 selects the next step for editing.
+//! * Button 2 toggles gate of the selected step.
+//! * Pot 1 and 2 set voltage of the first and second lane of the selected
+//!   step, between 0 and 5 V. After another step is selected, pots need to
+//!   be moved to take over.
+//! * Pot 3 sets length of the pattern.
+//! * Pot 4 selects one of 4 patterns.
+//! * Gate input 1 is an external clock. Without it, the sequencer follows
+//!   the internal clock.
+//! * Gate input 2 resets the sequencer to its first step.
+//! * CV output 1 and 2 play the first and second lane. Gate output 1 plays
+//!   gates of the steps, gate output 2 accents, being gates of steps with
+//!   the second lane above 2.5 V.
+//! * LED 1 to 4 show the playing step as a binary number. For 2 seconds
+//!   after an edit, they show the selected step instead.
+//!
+//! Patterns are saved 2 seconds after the last edit.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::hysteresis::zone_with_hysteresis;
+use crate::controller::output::Outputs;
+use crate::controller::pot_latch::PotLatch;
+
+pub const PATTERNS: usize = 4;
+pub const STEPS: usize = 16;
+
+pub struct Sequencer {
+    patterns: [Pattern; PATTERNS],
+    selected_pattern: usize,
+    position: usize,
+    cursor: usize,
+    reset: bool,
+    edit_countdown: u32,
+    save_countdown: u32,
+    save_requested: bool,
+    latches: [PotLatch; 3],
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    reset_detector: EdgeDetector,
+    cursor_detector: EdgeDetector,
+    gate_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Pattern {
+    pub steps: [Step; STEPS],
+    pub length: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Step {
+    pub lanes: [f32; 2],
+    pub gate: bool,
+}
+
+impl Sequencer {
+    const EDIT_DISPLAY: u32 = 2000;
+    const SAVE_DELAY: u32 = 2000;
+    // NOTE: Ignore jitter of pots, so they don't keep triggering saves.
+    const LANE_THRESHOLD: f32 = 0.005;
+
+    pub fn new(patterns: [Pattern; PATTERNS]) -> Self {
+        Self {
+            patterns,
+            selected_pattern: 0,
+            position: 0,
+            cursor: 0,
+            reset: true,
+            edit_countdown: 0,
+            save_countdown: 0,
+            save_requested: false,
+            latches: [PotLatch::new(), PotLatch::new(), PotLatch::new()],
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            reset_detector: EdgeDetector::new(),
+            cursor_detector: EdgeDetector::new(),
+            gate_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn patterns(&self) -> &[Pattern; PATTERNS] {
+        &self.patterns
+    }
+
+    /// Returns true once after edits settled and the patterns should be
+    /// saved.
+    pub fn take_save_request(&mut self) -> bool {
+        let requested = self.save_requested;
+        self.save_requested = false;
+        requested
+    }
+
+    /// To be called when the mode gets selected. Pots need to be moved
+    /// before they edit the pattern, not to overwrite it right away.
+    pub fn enter(&mut self, snapshot: &ControlInputSnapshot) {
+        for (i, latch) in self.latches.iter_mut().enumerate() {
+            latch.release(snapshot.pots[i]);
+        }
+    }
+
+    /// Count down to a save after edits. This runs whatever the selected
+    /// mode, so edits get saved even after leaving the sequencer.
+    pub fn tick_save(&mut self) {
+        if self.save_countdown > 0 {
+            self.save_countdown -= 1;
+            if self.save_countdown == 0 {
+                self.save_requested = true;
+            }
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let selected_pattern =
+            zone_with_hysteresis(self.selected_pattern, snapshot.pots[3], PATTERNS);
+        if selected_pattern != self.selected_pattern {
+            self.selected_pattern = selected_pattern;
+            self.cursor = 0;
+            for (i, latch) in self.latches.iter_mut().enumerate() {
+                latch.release(snapshot.pots[i]);
+            }
+        }
+
+        if self.cursor_detector.rising(snapshot.buttons[0]) {
+            let length = self.patterns[self.selected_pattern].length;
+            self.cursor = (self.cursor + 1) % length;
+            self.latches[0].release(snapshot.pots[0]);
+            self.latches[1].release(snapshot.pots[1]);
+            self.edit_countdown = Self::EDIT_DISPLAY;
+        }
+
+        if self.gate_detector.rising(snapshot.buttons[1]) {
+            let step = &mut self.patterns[self.selected_pattern].steps[self.cursor];
+            step.gate = !step.gate;
+            self.mark_edited();
+        }
+
+        for lane in 0..2 {
+            if let Some(value) = self.latches[lane].update(snapshot.pots[lane]) {
+                let voltage = value * 5.0;
+                let step = &mut self.patterns[self.selected_pattern].steps[self.cursor];
+                if libm::fabsf(step.lanes[lane] - voltage) > Self::LANE_THRESHOLD {
+                    step.lanes[lane] = voltage;
+                    self.mark_edited();
+                }
+            }
+        }
+
+        if let Some(value) = self.latches[2].update(snapshot.pots[2]) {
+            let length = 1 + (value * (STEPS as f32 - 0.01)) as usize;
+            let pattern = &mut self.patterns[self.selected_pattern];
+            if length != pattern.length {
+                pattern.length = length;
+                self.cursor %= length;
+                self.mark_edited();
+            }
+        }
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+
+        if self.reset_detector.rising(snapshot.gates[1]) {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        let pattern = &self.patterns[self.selected_pattern];
+
+        if self.clock_follower.tick(clock) {
+            if self.reset {
+                self.reset = false;
+                self.position = 0;
+            } else {
+                self.position = (self.position + 1) % pattern.length;
+            }
+            self.position %= pattern.length;
+
+            let step = &pattern.steps[self.position];
+            let gate_length = (self.clock_follower.period(clock) / 2.0) as usize;
+            if step.gate {
+                outputs.gates[0].enable_with_countdown(gate_length);
+                if step.lanes[1] > 2.5 {
+                    outputs.gates[1].enable_with_countdown(gate_length);
+                }
+            }
+        }
+
+        let step = &pattern.steps[self.position % pattern.length];
+        outputs.cvs[0].set_value(step.lanes[0]);
+        outputs.cvs[1].set_value(step.lanes[1]);
+
+        let displayed = if self.edit_countdown > 0 {
+            self.edit_countdown -= 1;
+            self.cursor
+        } else {
+            self.position
+        };
+        for (i, led) in outputs.leds.iter_mut().enumerate() {
+            led.set(displayed & (1 << i) != 0);
+        }
+    }
+
+    fn mark_edited(&mut self) {
+        self.edit_countdown = Self::EDIT_DISPLAY;
+        self.save_countdown = Self::SAVE_DELAY;
+    }
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Self {
+            steps: [Step::default(); STEPS],
+            length: STEPS,
+        }
+    }
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self {
+            lanes: [0.0, 0.0],
+            gate: true,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Sequential switch, routing one of the four CV inputs to the output.
+//!
+//! * Gate input 1 advances to the next CV input, gate input 2 resets back
+//!   to the first one. Button 1 advances manually.
+//! * Pot 1 selects the order: forward, backward, random or pendulum.
+//! * Pot 2 sets crossfade time between inputs, from 0 to 500 ms.
+//! * Pot 3 selects how the bipolar signal is mapped to the CV outputs (see
+//!   `OutputMapping`).
+//! * CV output 1 sends the selected input, crossfaded. CV output 2 sends it
+//!   without the crossfade.
+//! * Gate output 1 sends a trigger whenever the selection changes.
+//! * LED 1 to 4 show the selected input.
+//!
+//! Inputs that are not patched are skipped. When none is patched, all of
+//! them are cycled through.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::output::Outputs;
+use crate::controller::output_mapping::OutputMapping;
+use crate::controller::patch_detector::PatchDetector;
+use crate::random_generator::RandomGenerator;
+
+const INPUTS: usize = 4;
+
+pub struct SequentialSwitch {
+    order: Order,
+    fade_time: f32,
+    output_mapping: OutputMapping,
+    inputs: [f32; INPUTS],
+    selected: usize,
+    previous: usize,
+    fade: f32,
+    descending: bool,
+    advanced: bool,
+    reset: bool,
+    patch_detectors: [PatchDetector; INPUTS],
+    button_detector: EdgeDetector,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Order {
+    Forward,
+    Backward,
+    Random,
+    Pendulum,
+}
+
+impl SequentialSwitch {
+    const MAX_FADE_TIME: f32 = 500.0;
+
+    pub fn new() -> Self {
+        Self {
+            order: Order::Forward,
+            fade_time: 0.0,
+            output_mapping: OutputMapping::Clip,
+            inputs: [0.0; INPUTS],
+            selected: 0,
+            previous: 0,
+            fade: 1.0,
+            descending: false,
+            advanced: false,
+            reset: false,
+            patch_detectors: [
+                PatchDetector::new(),
+                PatchDetector::new(),
+                PatchDetector::new(),
+                PatchDetector::new(),
+            ],
+            button_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.order = Order::from_pot(snapshot.pots[0]);
+        self.fade_time = snapshot.pots[1] * Self::MAX_FADE_TIME;
+        self.output_mapping = OutputMapping::from_pot(snapshot.pots[2]);
+
+        for (i, detector) in self.patch_detectors.iter_mut().enumerate() {
+            detector.update(snapshot.cvs[i]);
+            self.inputs[i] = snapshot.cvs[i].unwrap_or(0.0);
+        }
+
+        // NOTE: Latched edges are used, so no trigger gets missed.
+        if snapshot.gate_triggers[0] || self.button_detector.rising(snapshot.buttons[0]) {
+            self.advanced = true;
+        }
+        if snapshot.gate_triggers[1] {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(&mut self, random_generator: &mut RandomGenerator, outputs: &mut Outputs) {
+        let patched = self.patched();
+
+        let selected = if self.reset {
+            self.descending = false;
+            patched.iter().position(|p| *p).unwrap_or(0)
+        } else if self.advanced {
+            self.next(&patched, random_generator)
+        } else {
+            self.selected
+        };
+        self.reset = false;
+        self.advanced = false;
+
+        if selected != self.selected {
+            self.previous = self.selected;
+            self.selected = selected;
+            self.fade = 0.0;
+            outputs.gates[0].enable_with_countdown(10);
+        }
+
+        if self.fade < 1.0 {
+            self.fade = if self.fade_time < 1.0 {
+                1.0
+            } else {
+                (self.fade + 1.0 / self.fade_time).min(1.0)
+            };
+        }
+
+        let current = self.inputs[self.selected];
+        let faded = self.inputs[self.previous] * (1.0 - self.fade) + current * self.fade;
+        outputs.cvs[0].set_value(self.output_mapping.apply(faded));
+        outputs.cvs[1].set_value(self.output_mapping.apply(current));
+
+        for (i, led) in outputs.leds.iter_mut().enumerate() {
+            led.set(i == self.selected);
+        }
+    }
+
+    fn patched(&self) -> [bool; INPUTS] {
+        let mut patched = [false; INPUTS];
+        for (p, detector) in patched.iter_mut().zip(self.patch_detectors.iter()) {
+            *p = detector.is_patched();
+        }
+        if patched.iter().any(|p| *p) {
+            patched
+        } else {
+            [true; INPUTS]
+        }
+    }
+
+    fn next(&mut self, patched: &[bool; INPUTS], random_generator: &mut RandomGenerator) -> usize {
+        let forward = |from: usize| {
+            (1..=INPUTS)
+                .map(|i| (from + i) % INPUTS)
+                .find(|i| patched[*i])
+        };
+        let backward = |from: usize| {
+            (1..=INPUTS)
+                .map(|i| (from + INPUTS - i) % INPUTS)
+                .find(|i| patched[*i])
+        };
+
+        let next = match self.order {
+            Order::Forward => forward(self.selected),
+            Order::Backward => backward(self.selected),
+            Order::Random => {
+                let count = patched.iter().filter(|p| **p).count();
+                let skip = random_generator.u16().unwrap_or_default() as usize % count;
+                patched
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| **p)
+                    .map(|(i, _)| i)
+                    .nth(skip)
+            }
+            Order::Pendulum => {
+                // NOTE: Turn around when passing over the last or the first
+                // patched input.
+                let (next, wrapped) = if self.descending {
+                    let next = backward(self.selected);
+                    (next, next.is_some_and(|n| n >= self.selected))
+                } else {
+                    let next = forward(self.selected);
+                    (next, next.is_some_and(|n| n <= self.selected))
+                };
+                if wrapped {
+                    self.descending = !self.descending;
+                    if self.descending {
+                        backward(self.selected)
+                    } else {
+                        forward(self.selected)
+                    }
+                } else {
+                    next
+                }
+            }
+        };
+
+        next.unwrap_or(self.selected)
+    }
+}
+
+impl Order {
+    fn from_pot(value: f32) -> Self {
+        match (value * 3.99) as usize {
+            0 => Self::Forward,
+            1 => Self::Backward,
+            2 => Self::Random,
+            _ => Self::Pendulum,
+        }
+    }
+}
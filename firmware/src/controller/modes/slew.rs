@@ -0,0 +1,93 @@
+//! Two channels of slew limiter with independent rise and fall.
+//!
+//! * CV input 1 and 2 are slewed and sent to CV output 1 and 2.
+//! * Pot 1 and 2 set rise and fall time of the first channel, pot 3 and 4
+//!   of the second channel. The time spans from 1 ms to 10 s over the
+//!   full range of the input.
+//! * Button 1 and 2 cycle through shapes of the first and second channel:
+//!   linear, exponential and logarithmic. After a press, LED 1 to 3 show
+//!   the selected shape for a second.
+//! * Gate output 1 and 2 fire at the end of each rise and fall of the
+//!   first and second channel.
+//! * LED 1 and 2 light up while the first channel rises and falls, LED 3
+//!   and 4 while the second channel does.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+use crate::controller::slew::{Movement, Shape, Slew as Limiter};
+
+pub struct Slew {
+    channels: [Channel; 2],
+    indicator: Indicator,
+}
+
+struct Channel {
+    input: f32,
+    shape: Shape,
+    limiter: Limiter,
+    shape_detector: EdgeDetector,
+}
+
+impl Slew {
+    pub fn new() -> Self {
+        Self {
+            channels: [Channel::new(), Channel::new()],
+            indicator: Indicator::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            channel.input = snapshot.cvs[i].unwrap_or(0.0);
+
+            let rise = pot_to_time(snapshot.pots[i * 2]);
+            let fall = pot_to_time(snapshot.pots[i * 2 + 1]);
+            channel.limiter.set_times(rise, fall);
+
+            if channel.shape_detector.rising(snapshot.buttons[i]) {
+                channel.shape = match channel.shape {
+                    Shape::Linear => Shape::Exponential,
+                    Shape::Exponential => Shape::Logarithmic,
+                    Shape::Logarithmic => Shape::Linear,
+                };
+                channel.limiter.set_shape(channel.shape);
+                self.indicator.indicate(channel.shape as usize);
+            }
+        }
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            if channel.limiter.tick(channel.input).is_some() {
+                outputs.gates[i].enable_with_countdown(10);
+            }
+            outputs.cvs[i].set_value(channel.limiter.value());
+        }
+
+        if !self.indicator.tick(outputs) {
+            for (i, channel) in self.channels.iter().enumerate() {
+                let movement = channel.limiter.movement();
+                outputs.leds[i * 2].set(movement == Movement::Rising);
+                outputs.leds[i * 2 + 1].set(movement == Movement::Falling);
+            }
+        }
+    }
+}
+
+impl Channel {
+    fn new() -> Self {
+        Self {
+            input: 0.0,
+            shape: Shape::Linear,
+            limiter: Limiter::new(),
+            shape_detector: EdgeDetector::new(),
+        }
+    }
+}
+
+/// Map pot position to time between 1 ms and 10 s.
+fn pot_to_time(value: f32) -> f32 {
+    0.001 * libm::powf(10_000.0, value)
+}
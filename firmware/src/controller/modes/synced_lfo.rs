@@ -0,0 +1,112 @@
+//! LFO locked to a musical ratio of the clock.
+//!
+//! * Pot 1 selects the ratio between the LFO and the clock, from 1/8 to 8
+//!   cycles per beat. CV input 1 is added to it.
+//! * Pot 2 selects the waveshape: sine, triangle, ramp up, ramp down,
+//!   square, stepped random or smooth random.
+//! * Pot 3 sets amplitude, between 0 and 5 V.
+//! * Pot 4 shifts phase of CV output 2 against CV output 1.
+//! * Gate input 1 is an external clock. Without it, the LFO follows the
+//!   internal clock.
+//! * Gate input 2 resets the LFO. It starts over on the next beat, from
+//!   the bottom of the waveshape, or the top in case of the ramp down.
+//! * Gate output 1 and LED 2 signal start of each LFO cycle, LED 1 blinks
+//!   on beats.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::lfo::{Lfo, Waveform};
+use crate::controller::output::Outputs;
+use crate::random_generator::RandomGenerator;
+
+pub struct SyncedLfo {
+    ratio: (u32, u32),
+    phase_shift: f32,
+    beat_count: u32,
+    reset: bool,
+    lfos: [Lfo; 2],
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    reset_detector: EdgeDetector,
+}
+
+impl SyncedLfo {
+    // NOTE: Pairs of LFO cycles and clock beats.
+    const RATIOS: [(u32, u32); 9] = [
+        (1, 8),
+        (1, 4),
+        (1, 3),
+        (1, 2),
+        (1, 1),
+        (2, 1),
+        (3, 1),
+        (4, 1),
+        (8, 1),
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            ratio: (1, 1),
+            phase_shift: 0.0,
+            beat_count: 0,
+            reset: false,
+            lfos: [Lfo::new(), Lfo::new()],
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            reset_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let ratio_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        let ratio = (snapshot.pots[0] + ratio_cv).clamp(0.0, 1.0);
+        self.ratio = Self::RATIOS[(ratio * (Self::RATIOS.len() as f32 - 0.01)) as usize];
+
+        for lfo in self.lfos.iter_mut() {
+            lfo.set_waveform(Waveform::from_pot(snapshot.pots[1]));
+            lfo.set_amplitude(snapshot.pots[2] * 5.0);
+        }
+        self.phase_shift = snapshot.pots[3];
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+
+        if self.reset_detector.rising(snapshot.gates[1]) {
+            self.reset = true;
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        clock: &Clock,
+        random_generator: &mut RandomGenerator,
+        outputs: &mut Outputs,
+    ) {
+        if self.clock_follower.tick(clock) {
+            if self.reset {
+                self.reset = false;
+                self.beat_count = 0;
+            } else {
+                self.beat_count = self.beat_count.wrapping_add(1);
+            }
+            outputs.leds[0].enable_with_countdown(30);
+        }
+
+        let (cycles, beats) = self.ratio;
+        let beat_phase = self.clock_follower.phase(clock);
+        let position = (self.beat_count % beats) as f32 + beat_phase;
+        let (phase, _) = libm::modff(position * cycles as f32 / beats as f32);
+        let (shifted_phase, _) = libm::modff(phase + self.phase_shift);
+
+        if self.lfos[0].sync(phase, random_generator) {
+            outputs.gates[0].enable_with_countdown(10);
+            outputs.leds[1].enable_with_countdown(30);
+        }
+        self.lfos[1].sync(shifted_phase, random_generator);
+
+        self.lfos[0].write(&mut outputs.cvs[0]);
+        self.lfos[1].write(&mut outputs.cvs[1]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Transposer, shifting V/oct signals by semitones and octaves.
+//!
+//! * CV input 1 and 2 are transposed and sent to CV output 1 and 2.
+//! * Pot 1 sets transposition from -12 to +12 semitones.
+//! * Pot 2 sets transposition from -2 to +2 octaves.
+//! * CV input 3 is added to the transposition, rounded to semitones.
+//! * Pot 3 sets glide time, up to 1 second per volt.
+//! * Pot 4 selects how notes outside of the 0 to 5 V output range are
+//!   handled: clipped, folded to the nearest octave that fits, or wrapped
+//!   around to the other end of the range.
+//! * LED 1 to 4 show the octave transposition as a bar growing from the
+//!   center to the left or right. All of them flash briefly on each step
+//!   of the semitone transposition.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::hysteresis::step_with_hysteresis;
+use crate::controller::output::Outputs;
+use crate::controller::slew::Slew;
+
+pub struct Transposer {
+    inputs: [f32; 2],
+    semitones: i32,
+    octaves: i32,
+    cv_semitones: i32,
+    range: Range,
+    flash: u32,
+    slews: [Slew; 2],
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Range {
+    Clip,
+    Fold,
+    Wrap,
+}
+
+impl Transposer {
+    const FLASH: u32 = 30;
+    // NOTE: Slew times are defined over its full range of 10 V.
+    const MAX_GLIDE: f32 = 10.0;
+
+    pub fn new() -> Self {
+        Self {
+            inputs: [0.0, 0.0],
+            semitones: 0,
+            octaves: 0,
+            cv_semitones: 0,
+            range: Range::Clip,
+            flash: 0,
+            slews: [Slew::new(), Slew::new()],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        self.inputs = [
+            snapshot.cvs[0].unwrap_or(0.0),
+            snapshot.cvs[1].unwrap_or(0.0),
+        ];
+
+        let semitones = step_with_hysteresis(self.semitones, snapshot.pots[0] * 24.0 - 12.0);
+        if semitones != self.semitones {
+            self.semitones = semitones;
+            self.flash = Self::FLASH;
+        }
+        self.octaves = step_with_hysteresis(self.octaves, snapshot.pots[1] * 4.0 - 2.0);
+        self.cv_semitones =
+            step_with_hysteresis(self.cv_semitones, snapshot.cvs[2].unwrap_or(0.0) * 12.0);
+
+        let glide = snapshot.pots[2] * Self::MAX_GLIDE;
+        for slew in self.slews.iter_mut() {
+            slew.set_times(glide, glide);
+        }
+
+        self.range = Range::from_pot(snapshot.pots[3]);
+    }
+
+    pub fn tick(&mut self, outputs: &mut Outputs) {
+        let transposition = (self.semitones + self.octaves * 12 + self.cv_semitones) as f32 / 12.0;
+
+        for i in 0..2 {
+            // NOTE: The range is applied after the glide, so notes folded or
+            // wrapped to the other end of the range don't glide across it.
+            self.slews[i].tick(self.inputs[i] + transposition);
+            outputs.cvs[i].set_value(self.range.apply(self.slews[i].value()));
+        }
+
+        if self.flash > 0 {
+            self.flash -= 1;
+            for led in outputs.leds.iter_mut() {
+                led.set(true);
+            }
+        } else {
+            outputs.leds[0].set(self.octaves <= -2);
+            outputs.leds[1].set(self.octaves <= -1);
+            outputs.leds[2].set(self.octaves >= 1);
+            outputs.leds[3].set(self.octaves >= 2);
+        }
+    }
+}
+
+impl Range {
+    const TOP: f32 = 5.0;
+
+    fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Clip,
+            1 => Self::Fold,
+            _ => Self::Wrap,
+        }
+    }
+
+    fn apply(self, voct: f32) -> f32 {
+        match self {
+            Self::Clip => voct.clamp(0.0, Self::TOP),
+            Self::Fold => {
+                if voct > Self::TOP {
+                    voct - libm::ceilf(voct - Self::TOP)
+                } else if voct < 0.0 {
+                    voct + libm::ceilf(-voct)
+                } else {
+                    voct
+                }
+            }
+            // NOTE: The range spans whole octaves, so wrapping around keeps
+            // the pitch class.
+            Self::Wrap => voct - libm::floorf(voct / Self::TOP) * Self::TOP,
+        }
+    }
+}
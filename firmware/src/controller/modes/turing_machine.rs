@@ -0,0 +1,147 @@
+//! Looping random shift register, also known as the Turing machine.
+//!
+//! On each clock, the register shifts and the bit falling off its end is
+//! fed back to its start, possibly flipped.
+//!
+//! * Pot 1 sets probability of flipping the bit. Fully left, the loop is
+//!   locked. In the center, the bits are fully random. Fully right, every
+//!   bit gets flipped, locking the loop to twice its length with the second
+//!   half inverted. CV input 1 is added to it.
+//! * Pot 2 sets length of the loop, from 1 to 16 steps.
+//! * Pot 3 sets range of the CV outputs, from 0 to 5 V.
+//! * Pot 4 selects quantization of the CV outputs: none, chromatic, major,
+//!   minor, major pentatonic or minor pentatonic.
+//! * Gate input 1 is an external clock. Without it, the register follows
+//!   the internal clock.
+//! * While button 2 is held, ones are written into the register.
+//! * CV output 1 follows the lowest 8 bits of the register, CV output 2
+//!   the 8 bits starting from bit 4.
+//! * Gate output 1 fires when bit 0 is set. Gate output 2 fires when the
+//!   selected bit is set, button 1 cycles the selection between bits 1 to
+//!   4, shown on LEDs for a second.
+//! * LED 1 to 4 show bits 0 to 3 of the register.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::{Clock, ClockFollower};
+use crate::controller::edge_detector::EdgeDetector;
+use crate::controller::indicator::Indicator;
+use crate::controller::output::Outputs;
+use crate::controller::quantizer::{self, Quantizer, Scale};
+use crate::random_generator::RandomGenerator;
+
+pub struct TuringMachine {
+    register: u16,
+    seeded: bool,
+    length: u32,
+    probability: f32,
+    range: f32,
+    scale: Option<Scale>,
+    write: bool,
+    gate_2_bit: u32,
+    quantizers: [Quantizer; 2],
+    indicator: Indicator,
+    clock_follower: ClockFollower,
+    clock_detector: EdgeDetector,
+    bit_detector: EdgeDetector,
+}
+
+impl TuringMachine {
+    const SCALES: [Option<Scale>; 6] = [
+        None,
+        Some(Scale::CHROMATIC),
+        Some(Scale::MAJOR),
+        Some(Scale::MINOR),
+        Some(Scale::MAJOR_PENTATONIC),
+        Some(Scale::MINOR_PENTATONIC),
+    ];
+
+    pub fn new() -> Self {
+        Self {
+            register: 0,
+            seeded: false,
+            length: 16,
+            probability: 0.0,
+            range: 5.0,
+            scale: None,
+            write: false,
+            gate_2_bit: 1,
+            quantizers: [Quantizer::new(), Quantizer::new()],
+            indicator: Indicator::new(),
+            clock_follower: ClockFollower::new(),
+            clock_detector: EdgeDetector::new(),
+            bit_detector: EdgeDetector::new(),
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot) {
+        let probability_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        self.probability = (snapshot.pots[0] + probability_cv).clamp(0.0, 1.0);
+        self.length = 1 + (snapshot.pots[1] * 15.99) as u32;
+        self.range = snapshot.pots[2] * 5.0;
+        let scale_index = (snapshot.pots[3] * (Self::SCALES.len() as f32 - 0.01)) as usize;
+        self.scale = Self::SCALES[scale_index];
+        self.write = snapshot.buttons[1];
+
+        if self.bit_detector.rising(snapshot.buttons[0]) {
+            self.gate_2_bit = self.gate_2_bit % 4 + 1;
+            self.indicator.indicate(self.gate_2_bit as usize - 1);
+        }
+
+        if self.clock_detector.rising(snapshot.gates[0]) {
+            self.clock_follower.trigger();
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        clock: &Clock,
+        random_generator: &mut RandomGenerator,
+        outputs: &mut Outputs,
+    ) {
+        if self.clock_follower.tick(clock) {
+            self.shift(random_generator);
+            if self.bit(0) {
+                outputs.gates[0].enable_with_countdown(10);
+            }
+            if self.bit(self.gate_2_bit) {
+                outputs.gates[1].enable_with_countdown(10);
+            }
+        }
+
+        let windows = [self.register & 0xFF, (self.register >> 4) & 0xFF];
+        for (i, window) in windows.iter().enumerate() {
+            let value = *window as f32 / 255.0 * self.range;
+            let value = match &self.scale {
+                Some(scale) => quantizer::note_to_voct(self.quantizers[i].quantize(value, scale)),
+                None => value,
+            };
+            outputs.cvs[i].set_value(value);
+        }
+
+        if !self.indicator.tick(outputs) {
+            for (i, led) in outputs.leds.iter_mut().enumerate() {
+                led.set(self.bit(i as u32));
+            }
+        }
+    }
+
+    fn shift(&mut self, random_generator: &mut RandomGenerator) {
+        if !self.seeded {
+            self.seeded = true;
+            self.register = random_generator.u16().unwrap_or_default();
+        }
+
+        let mut bit = self.bit(self.length - 1);
+        if random_generator.f32() < self.probability {
+            bit = !bit;
+        }
+        if self.write {
+            bit = true;
+        }
+        self.register = (self.register << 1) | u16::from(bit);
+    }
+
+    fn bit(&self, index: u32) -> bool {
+        self.register & (1 << index) != 0
+    }
+}
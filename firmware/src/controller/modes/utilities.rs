@@ -0,0 +1,312 @@
+//! Clock generator, clock divider, attenuverter and a steady CV source.
+//!
+//! * Pot 1 sets division of the second clock. CV input 1 is added to it.
+//! * Pot 2 sets speed of the internal clock.
+//! * Pot 3 attenuates and inverts CV input 3 and sends it to CV output 2.
+//!   The center of the pot mutes the signal, turning it right passes it
+//!   through, turning it left inverts it.
+//! * Pot 4 sets voltage of CV output 1.
+//!
+//! While button 1 is held, LED 3 lights up and pot 1 and 2 set gate length
+//! of gate output 1 and 2. The left half of the pot sets a fixed length
+//! between 1 and 250 ms, the right half sets duty cycle relative to the
+//! clock period. The center of the right half locks to a 50 % square.
+//! Either way, the length follows changes of the tempo. A fixed length
+//! longer than the period gets shortened, so the gate still falls before
+//! the next one.
+//!
+//! While button 2 is held, LED 4 lights up and the module can be
+//! configured:
+//!
+//! * Pot 1 selects the table of divisions: linear from 1 to 9, primes from
+//!   1 to 23, or powers of two from 1 to 64.
+//! * Pot 2 selects when a change of the division takes effect: immediately,
+//!   on the next downbeat of the second clock, or immediately while keeping
+//!   the relative position within the divided period.
+//! * Pot 3 selects how the attenuverted signal is mapped to CV output 2
+//!   (see `OutputMapping`).
+//! * Pot 4 sets offset between -5 and +5 V added to the attenuverted signal
+//!   before the mapping.
+//!
+//! After a button is released, pots need to be moved to take over their
+//! primary function.
+
+use crate::control_input::ControlInputSnapshot;
+use crate::controller::clock::Clock;
+use crate::controller::hysteresis::zone_with_hysteresis;
+use crate::controller::output::Outputs;
+use crate::controller::output_mapping::OutputMapping;
+use crate::controller::pot_latch::PotLatch;
+
+pub struct Utilities {
+    clock_2_phase: u8,
+    clock_2_division: u8,
+    division_index: usize,
+    division_pot: f32,
+    division_cv: f32,
+    divisions: Divisions,
+    rate_change: RateChange,
+    attenuverter_input: f32,
+    attenuversion: f32,
+    offset: f32,
+    output_mapping: OutputMapping,
+    cv_generator_steady: f32,
+    gate_lengths: [GateLength; 2],
+    layer: Layer,
+    latches: [PotLatch; 4],
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum GateLength {
+    // NOTE: Length in ticks.
+    Fixed(f32),
+    // NOTE: Portion of the period, between 0.0 and 1.0.
+    Duty(f32),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Divisions {
+    Linear,
+    Prime,
+    PowerOfTwo,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RateChange {
+    Immediate,
+    Downbeat,
+    PhasePreserving,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Primary,
+    GateLength,
+    Configuration,
+}
+
+impl Utilities {
+    pub fn new() -> Self {
+        Self {
+            clock_2_phase: 0,
+            clock_2_division: 1,
+            division_index: 0,
+            division_pot: 0.0,
+            division_cv: 0.0,
+            divisions: Divisions::Linear,
+            rate_change: RateChange::Immediate,
+            attenuverter_input: 0.0,
+            attenuversion: 0.0,
+            offset: 0.0,
+            output_mapping: OutputMapping::Clip,
+            cv_generator_steady: 0.0,
+            gate_lengths: [GateLength::Fixed(10.0), GateLength::Fixed(10.0)],
+            layer: Layer::Primary,
+            latches: [
+                PotLatch::new(),
+                PotLatch::new(),
+                PotLatch::new(),
+                PotLatch::new(),
+            ],
+        }
+    }
+
+    pub fn apply_input_snapshot(&mut self, snapshot: &ControlInputSnapshot, clock: &mut Clock) {
+        let layer = Layer::from_buttons(snapshot.buttons);
+        if layer != self.layer {
+            for i in self.layer.pots().iter().chain(layer.pots()) {
+                self.latches[*i].release(snapshot.pots[*i]);
+            }
+            self.layer = layer;
+        }
+
+        for i in 0..self.latches.len() {
+            if let Some(value) = self.latches[i].update(snapshot.pots[i]) {
+                self.set_pot(i, value, clock);
+            }
+        }
+
+        self.division_cv = snapshot.cvs[0].unwrap_or(0.0) / 5.0;
+        self.attenuverter_input = snapshot.cvs[2].unwrap_or(0.0);
+    }
+
+    fn set_pot(&mut self, pot: usize, value: f32, clock: &mut Clock) {
+        match (self.layer, pot) {
+            (Layer::GateLength, 0 | 1) => {
+                self.gate_lengths[pot] = GateLength::from_pot(value);
+            }
+            (Layer::Configuration, 0) => {
+                self.divisions = Divisions::from_pot(value);
+            }
+            (Layer::Configuration, 1) => {
+                self.rate_change = RateChange::from_pot(value);
+            }
+            (Layer::Configuration, 2) => {
+                self.output_mapping = OutputMapping::from_pot(value);
+            }
+            (Layer::Configuration, 3) => {
+                self.offset = bipolar_with_dead_zone(value) * 5.0;
+            }
+            (_, 0) => {
+                self.division_pot = value;
+            }
+            (_, 1) => {
+                // Pot should move from output every 100 ms to every 2000 ms
+                // Meaning the speed (revolutions per second) should be between 100 and 0.5.
+                const MIN: f32 = 0.5;
+                const MAX: f32 = 10.0;
+                clock.set_speed(MIN + value * (MAX - MIN));
+            }
+            (_, 2) => {
+                self.attenuversion = bipolar_with_dead_zone(value);
+            }
+            _ => {
+                self.cv_generator_steady = value * 5.0;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, clock: &Clock, outputs: &mut Outputs) {
+        let table = self.divisions.table();
+        let value = (self.division_pot + self.division_cv).clamp(0.0, 1.0);
+        self.division_index = zone_with_hysteresis(self.division_index, value, table.len());
+        let division = table[self.division_index];
+        if division != self.clock_2_division {
+            self.change_division(division, clock);
+        }
+
+        if clock.beat() {
+            self.clock_2_phase += 1;
+            outputs.leds[0].enable_with_countdown(30);
+        }
+
+        if self.clock_2_phase >= self.clock_2_division {
+            self.clock_2_phase = 0;
+            outputs.leds[1].enable_with_countdown(30);
+            if self.rate_change == RateChange::Downbeat {
+                self.clock_2_division = division;
+            }
+        }
+
+        let division = self.clock_2_division as f32;
+        let clock_2_phase = (self.clock_2_phase as f32 + clock.phase()) / division;
+        let clock_2_period = clock.period() * division;
+        outputs.gates[0].set(self.gate_lengths[0].is_high(clock.phase(), clock.period()));
+        outputs.gates[1].set(self.gate_lengths[1].is_high(clock_2_phase, clock_2_period));
+        outputs.leds[2].set(self.layer == Layer::GateLength);
+        outputs.leds[3].set(self.layer == Layer::Configuration);
+
+        outputs.cvs[0].set_value(self.cv_generator_steady);
+        let attenuverted = self.attenuverter_input * self.attenuversion + self.offset;
+        outputs.cvs[1].set_value(self.output_mapping.apply(attenuverted));
+    }
+
+    fn change_division(&mut self, division: u8, clock: &Clock) {
+        match self.rate_change {
+            // NOTE: If the second clock is already past the new division,
+            // it fires right away.
+            RateChange::Immediate => self.clock_2_division = division,
+            // NOTE: The division gets applied once the period ends.
+            RateChange::Downbeat => (),
+            RateChange::PhasePreserving => {
+                let phase =
+                    (self.clock_2_phase as f32 + clock.phase()) / self.clock_2_division as f32;
+                self.clock_2_phase = (phase * division as f32) as u8;
+                self.clock_2_division = division;
+            }
+        }
+    }
+}
+
+impl Divisions {
+    const LINEAR: [u8; 9] = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+    const PRIME: [u8; 10] = [1, 2, 3, 5, 7, 11, 13, 17, 19, 23];
+    const POWER_OF_TWO: [u8; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+    fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Linear,
+            1 => Self::Prime,
+            _ => Self::PowerOfTwo,
+        }
+    }
+
+    fn table(self) -> &'static [u8] {
+        match self {
+            Self::Linear => &Self::LINEAR,
+            Self::Prime => &Self::PRIME,
+            Self::PowerOfTwo => &Self::POWER_OF_TWO,
+        }
+    }
+}
+
+impl RateChange {
+    fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Immediate,
+            1 => Self::Downbeat,
+            _ => Self::PhasePreserving,
+        }
+    }
+}
+
+impl GateLength {
+    fn from_pot(value: f32) -> Self {
+        if value < 0.5 {
+            const MIN: f32 = 1.0;
+            const MAX: f32 = 250.0;
+            let x = value * 2.0;
+            Self::Fixed(MIN + x * x * (MAX - MIN))
+        } else {
+            let duty = (value - 0.5) * 2.0;
+            // NOTE: Snap to a true square around the center.
+            if libm::fabsf(duty - 0.5) < 0.05 {
+                Self::Duty(0.5)
+            } else {
+                Self::Duty(duty.clamp(0.01, 0.99))
+            }
+        }
+    }
+
+    fn is_high(self, phase: f32, period: f32) -> bool {
+        match self {
+            // NOTE: Keep at least one tick low, so the next gate has an edge.
+            Self::Fixed(length) => phase * period < length.min(period - 1.0),
+            Self::Duty(duty) => phase < duty,
+        }
+    }
+}
+
+impl Layer {
+    fn from_buttons(buttons: [bool; 2]) -> Self {
+        if buttons[0] {
+            Self::GateLength
+        } else if buttons[1] {
+            Self::Configuration
+        } else {
+            Self::Primary
+        }
+    }
+
+    /// Pots taken over from the primary layer.
+    fn pots(self) -> &'static [usize] {
+        match self {
+            Self::Primary => &[],
+            Self::GateLength => &[0, 1],
+            Self::Configuration => &[0, 1, 2, 3],
+        }
+    }
+}
+
+/// Map pot position to -1.0 to +1.0, with a dead zone in the center
+/// making it easy to hit zero.
+fn bipolar_with_dead_zone(value: f32) -> f32 {
+    const DEAD_ZONE: f32 = 0.05;
+    let bipolar = value * 2.0 - 1.0;
+    if libm::fabsf(bipolar) < DEAD_ZONE {
+        0.0
+    } else {
+        let magnitude = (libm::fabsf(bipolar) - DEAD_ZONE) / (1.0 - DEAD_ZONE);
+        libm::copysignf(magnitude, bipolar)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::control_output::ControlOutputState;
+
+pub struct Outputs {
+    pub leds: [BinaryOutput; 4],
+    pub gates: [BinaryOutput; 2],
+    pub cvs: [LinearOutput; 2],
+}
+
+pub struct BinaryOutput {
+    on: bool,
+    countdown: usize,
+}
+
+pub struct LinearOutput {
+    value: f32,
+}
+
+impl Outputs {
+    pub fn new() -> Self {
+        Self {
+            leds: [
+                BinaryOutput::new(),
+                BinaryOutput::new(),
+                BinaryOutput::new(),
+                BinaryOutput::new(),
+            ],
+            gates: [BinaryOutput::new(), BinaryOutput::new()],
+            cvs: [LinearOutput::new(), LinearOutput::new()],
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.leds[0].tick();
+        self.leds[1].tick();
+        self.leds[2].tick();
+        self.leds[3].tick();
+        self.gates[0].tick();
+        self.gates[1].tick();
+    }
+
+    pub fn state(&self) -> ControlOutputState {
+        ControlOutputState {
+            leds: [
+                self.leds[0].value(),
+                self.leds[1].value(),
+                self.leds[2].value(),
+                self.leds[3].value(),
+            ],
+            gates: [self.gates[0].value(), self.gates[1].value()],
+            cvs: [self.cvs[0].value(), self.cvs[1].value()],
+        }
+    }
+}
+
+impl BinaryOutput {
+    pub fn new() -> Self {
+        Self {
+            on: false,
+            countdown: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            if self.countdown == 0 {
+                self.on = false;
+            }
+        }
+    }
+
+    pub fn value(&self) -> bool {
+        self.on
+    }
+
+    pub fn enable_with_countdown(&mut self, countdown: usize) {
+        self.on = true;
+        self.countdown = countdown;
+    }
+
+    pub fn set(&mut self, on: bool) {
+        self.on = on;
+        self.countdown = 0;
+    }
+}
+
+impl LinearOutput {
+    pub fn new() -> Self {
+        Self { value: 0.0 }
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value;
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
@@ -0,0 +1,37 @@
+/// Maps a bipolar signal into the 0 to 5 V range of CV outputs.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputMapping {
+    /// Voltage is passed through, anything outside of 0 to 5 V is clipped.
+    /// The negative half of a bipolar signal is lost.
+    Clip,
+    /// Voltage is shifted up by 2.5 V, then clipped. Signals within ±2.5 V
+    /// fit in without being scaled, which keeps V/oct tracking intact.
+    Shift,
+    /// The -5 to +5 V range is scaled down to 0 to 5 V, 0 V ending up at
+    /// 2.5 V. Nothing gets clipped, but the signal is halved.
+    Scale,
+}
+
+impl OutputMapping {
+    pub fn from_pot(value: f32) -> Self {
+        match (value * 2.99) as usize {
+            0 => Self::Clip,
+            1 => Self::Shift,
+            _ => Self::Scale,
+        }
+    }
+
+    pub fn apply(self, value: f32) -> f32 {
+        self.apply_unclipped(value).clamp(0.0, 5.0)
+    }
+
+    /// Map the value without clipping it to the output range, e.g. to
+    /// find out whether it would get clipped.
+    pub fn apply_unclipped(self, value: f32) -> f32 {
+        match self {
+            Self::Clip => value,
+            Self::Shift => value + 2.5,
+            Self::Scale => value / 2.0 + 2.5,
+        }
+    }
+}
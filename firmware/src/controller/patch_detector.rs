@@ -0,0 +1,31 @@
+/// Guesses whether a CV input is patched.
+///
+/// CV inputs have no jack detection and an unpatched input rests at 0 V.
+/// An input staying close to 0 V for over a second is therefore considered
+/// unpatched.
+pub struct PatchDetector {
+    idle_ticks: u32,
+}
+
+impl PatchDetector {
+    const THRESHOLD: f32 = 0.05;
+    const TIMEOUT: u32 = 1000;
+
+    pub fn new() -> Self {
+        Self {
+            idle_ticks: Self::TIMEOUT,
+        }
+    }
+
+    /// Update with a new sample of the input, expected at the control rate.
+    pub fn update(&mut self, value: Option<f32>) {
+        match value {
+            Some(value) if libm::fabsf(value) > Self::THRESHOLD => self.idle_ticks = 0,
+            _ => self.idle_ticks = self.idle_ticks.saturating_add(1),
+        }
+    }
+
+    pub fn is_patched(&self) -> bool {
+        self.idle_ticks < Self::TIMEOUT
+    }
+}
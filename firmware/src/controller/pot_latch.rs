@@ -0,0 +1,38 @@
+/// Lets a pot control parameters on multiple layers.
+///
+/// After a layer is switched, the pot is ignored until it is moved, so
+/// parameters of the newly selected layer do not jump to the position left
+/// by the previous one.
+pub struct PotLatch {
+    reference: f32,
+    active: bool,
+}
+
+impl PotLatch {
+    const THRESHOLD: f32 = 0.02;
+
+    pub fn new() -> Self {
+        Self {
+            reference: 0.0,
+            active: true,
+        }
+    }
+
+    /// Ignore the pot until it moves away from the given position.
+    pub fn release(&mut self, value: f32) {
+        self.reference = value;
+        self.active = false;
+    }
+
+    /// Returns the value of the pot if it was moved since the release.
+    pub fn update(&mut self, value: f32) -> Option<f32> {
+        if !self.active && libm::fabsf(value - self.reference) > Self::THRESHOLD {
+            self.active = true;
+        }
+        if self.active {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Pitch quantization to musical scales.
+
+/// Set of notes within an octave, bit 0 being the root.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Scale {
+    mask: u16,
+}
+
+/// Snaps V/oct signal to the nearest note of a scale, with a hysteresis
+/// preventing jitter on boundaries between notes.
+pub struct Quantizer {
+    note: Option<i32>,
+}
+
+impl Scale {
+    pub const CHROMATIC: Self = Self::from_notes(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+    pub const MAJOR: Self = Self::from_notes(&[0, 2, 4, 5, 7, 9, 11]);
+    pub const MINOR: Self = Self::from_notes(&[0, 2, 3, 5, 7, 8, 10]);
+    pub const MAJOR_PENTATONIC: Self = Self::from_notes(&[0, 2, 4, 7, 9]);
+    pub const MINOR_PENTATONIC: Self = Self::from_notes(&[0, 3, 5, 7, 10]);
+    pub const DORIAN: Self = Self::from_notes(&[0, 2, 3, 5, 7, 9, 10]);
+    pub const PHRYGIAN: Self = Self::from_notes(&[0, 1, 3, 5, 7, 8, 10]);
+    pub const LYDIAN: Self = Self::from_notes(&[0, 2, 4, 6, 7, 9, 11]);
+    pub const MIXOLYDIAN: Self = Self::from_notes(&[0, 2, 4, 5, 7, 9, 10]);
+    pub const LOCRIAN: Self = Self::from_notes(&[0, 1, 3, 5, 6, 8, 10]);
+
+    pub const fn empty() -> Self {
+        Self { mask: 0 }
+    }
+
+    const fn from_notes(notes: &[u8]) -> Self {
+        let mut mask = 0;
+        let mut i = 0;
+        while i < notes.len() {
+            mask |= 1 << notes[i];
+            i += 1;
+        }
+        Self { mask }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Whether the given note, in semitones from the root, is part of the
+    /// scale. Notes in other octaves are accepted too.
+    pub fn contains(&self, note: i32) -> bool {
+        self.mask & (1 << note.rem_euclid(12)) != 0
+    }
+
+    pub fn toggle(&mut self, note: i32) {
+        self.mask ^= 1 << note.rem_euclid(12);
+    }
+
+    /// Find the note of the scale closest to the given pitch in semitones.
+    /// Empty scale is treated as chromatic.
+    pub fn nearest(&self, semitones: f32) -> i32 {
+        if self.is_empty() {
+            return libm::roundf(semitones) as i32;
+        }
+
+        let center = libm::roundf(semitones) as i32;
+        let mut best = center;
+        let mut best_distance = f32::MAX;
+        for distance in 0..=12 {
+            for candidate in [center - distance, center + distance] {
+                let candidate_distance = libm::fabsf(candidate as f32 - semitones);
+                if self.contains(candidate) && candidate_distance < best_distance {
+                    best = candidate;
+                    best_distance = candidate_distance;
+                }
+            }
+        }
+        best
+    }
+}
+
+impl Quantizer {
+    // NOTE: How much closer, in semitones, must another note be to replace
+    // the current one.
+    const HYSTERESIS: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self { note: None }
+    }
+
+    /// Quantize V/oct signal, returning the note in semitones.
+    pub fn quantize(&mut self, voct: f32, scale: &Scale) -> i32 {
+        let semitones = voct * 12.0;
+        let candidate = scale.nearest(semitones);
+
+        let note = match self.note {
+            Some(current)
+                if current != candidate
+                    && (scale.contains(current) || scale.is_empty())
+                    && libm::fabsf(current as f32 - semitones)
+                        - libm::fabsf(candidate as f32 - semitones)
+                        < Self::HYSTERESIS =>
+            {
+                current
+            }
+            _ => candidate,
+        };
+
+        self.note = Some(note);
+        note
+    }
+}
+
+/// Convert note in semitones to V/oct.
+pub fn note_to_voct(note: i32) -> f32 {
+    note as f32 / 12.0
+}
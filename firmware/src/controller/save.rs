@@ -0,0 +1,212 @@
+//! State of the controller that persists between power cycles.
+//!
+//! The save is serialized into a fixed-size byte array, prefixed with a
+//! magic word and a version and suffixed with a checksum. Anything that
+//! does not pass these checks, such as the content of erased flash, is
+//! ignored and the default save is used instead.
+//!
+//! Saves of version 2 differ only by missing the calibration. They are
+//! still loaded, with the default calibration.
+
+use crate::controller::clock::Clock;
+use crate::controller::modes::sequencer::{Pattern, PATTERNS, STEPS};
+
+const MAGIC: [u8; 2] = *b"HN";
+const VERSION: u8 = 3;
+const VERSION_WITHOUT_CALIBRATIONS: u8 = 2;
+
+const HEADER_SIZE: usize = 3;
+const STEP_SIZE: usize = 5;
+const PATTERN_SIZE: usize = 1 + STEPS * STEP_SIZE;
+const CLOCK_START: usize = HEADER_SIZE + PATTERNS * PATTERN_SIZE;
+const CLOCK_SIZE: usize = 4;
+const CALIBRATIONS_START: usize = CLOCK_START + CLOCK_SIZE;
+const CALIBRATION_SIZE: usize = 8;
+const CALIBRATIONS_SIZE: usize = (INPUTS + OUTPUTS) * CALIBRATION_SIZE;
+const CHECKSUM_SIZE: usize = 2;
+
+pub const SAVE_SIZE: usize = CALIBRATIONS_START + CALIBRATIONS_SIZE + CHECKSUM_SIZE;
+
+const INPUTS: usize = 4;
+const OUTPUTS: usize = 2;
+
+#[derive(Clone, Copy)]
+pub struct Save {
+    pub patterns: [Pattern; PATTERNS],
+    pub clock_speed: f32,
+    pub calibrations: Calibrations,
+}
+
+/// Per-unit compensation of CV inputs and outputs, see `Calibrator`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Calibrations {
+    pub inputs: [Calibration; INPUTS],
+    pub outputs: [Calibration; OUTPUTS],
+}
+
+/// CV inputs are read as `(value + offset) * scale`, CV outputs are
+/// written as `value * scale + offset`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub offset: f32,
+    pub scale: f32,
+}
+
+impl Save {
+    pub fn to_bytes(&self) -> [u8; SAVE_SIZE] {
+        let mut bytes = [0; SAVE_SIZE];
+        bytes[..2].copy_from_slice(&MAGIC);
+        bytes[2] = VERSION;
+
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            let start = HEADER_SIZE + i * PATTERN_SIZE;
+            bytes[start] = pattern.length as u8;
+            for (j, step) in pattern.steps.iter().enumerate() {
+                let offset = start + 1 + j * STEP_SIZE;
+                bytes[offset..offset + 2]
+                    .copy_from_slice(&voltage_to_u16(step.lanes[0]).to_le_bytes());
+                bytes[offset + 2..offset + 4]
+                    .copy_from_slice(&voltage_to_u16(step.lanes[1]).to_le_bytes());
+                bytes[offset + 4] = step.gate as u8;
+            }
+        }
+
+        bytes[CLOCK_START..CLOCK_START + CLOCK_SIZE]
+            .copy_from_slice(&self.clock_speed.to_le_bytes());
+
+        let calibrations = self
+            .calibrations
+            .inputs
+            .iter()
+            .chain(self.calibrations.outputs.iter());
+        for (i, calibration) in calibrations.enumerate() {
+            let offset = CALIBRATIONS_START + i * CALIBRATION_SIZE;
+            bytes[offset..offset + 4].copy_from_slice(&calibration.offset.to_le_bytes());
+            bytes[offset + 4..offset + 8].copy_from_slice(&calibration.scale.to_le_bytes());
+        }
+
+        let checksum = checksum(&bytes[..SAVE_SIZE - CHECKSUM_SIZE]);
+        bytes[SAVE_SIZE - CHECKSUM_SIZE..].copy_from_slice(&checksum.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; SAVE_SIZE]) -> Option<Self> {
+        if bytes[..2] != MAGIC {
+            return None;
+        }
+        let checksum_start = match bytes[2] {
+            VERSION => SAVE_SIZE - CHECKSUM_SIZE,
+            VERSION_WITHOUT_CALIBRATIONS => CALIBRATIONS_START,
+            _ => return None,
+        };
+
+        let stored_checksum =
+            u16::from_le_bytes([bytes[checksum_start], bytes[checksum_start + 1]]);
+        if checksum(&bytes[..checksum_start]) != stored_checksum {
+            return None;
+        }
+
+        let mut save = Self::default();
+        for (i, pattern) in save.patterns.iter_mut().enumerate() {
+            let start = HEADER_SIZE + i * PATTERN_SIZE;
+            pattern.length = (bytes[start] as usize).clamp(1, STEPS);
+            for (j, step) in pattern.steps.iter_mut().enumerate() {
+                let offset = start + 1 + j * STEP_SIZE;
+                step.lanes[0] =
+                    u16_to_voltage(u16::from_le_bytes([bytes[offset], bytes[offset + 1]]));
+                step.lanes[1] =
+                    u16_to_voltage(u16::from_le_bytes([bytes[offset + 2], bytes[offset + 3]]));
+                step.gate = bytes[offset + 4] != 0;
+            }
+        }
+
+        let clock_speed = f32_from_le_bytes(&bytes[CLOCK_START..CLOCK_START + CLOCK_SIZE]);
+        if clock_speed.is_finite() && clock_speed > 0.0 {
+            save.clock_speed = clock_speed;
+        }
+
+        if bytes[2] == VERSION_WITHOUT_CALIBRATIONS {
+            return Some(save);
+        }
+
+        let calibrations = save
+            .calibrations
+            .inputs
+            .iter_mut()
+            .chain(save.calibrations.outputs.iter_mut());
+        for (i, calibration) in calibrations.enumerate() {
+            let offset = CALIBRATIONS_START + i * CALIBRATION_SIZE;
+            let loaded = Calibration {
+                offset: f32_from_le_bytes(&bytes[offset..offset + 4]),
+                scale: f32_from_le_bytes(&bytes[offset + 4..offset + 8]),
+            };
+            if loaded.is_plausible() {
+                *calibration = loaded;
+            }
+        }
+
+        Some(save)
+    }
+}
+
+impl Default for Save {
+    fn default() -> Self {
+        Self {
+            patterns: [Pattern::default(); PATTERNS],
+            clock_speed: Clock::DEFAULT_SPEED,
+            calibrations: Calibrations::default(),
+        }
+    }
+}
+
+impl Default for Calibrations {
+    fn default() -> Self {
+        Self {
+            // NOTE: Based on the measuring, most of the CV inputs actually
+            // rest at -0.02 V and their real span is -4.98 to +4.98 V. This
+            // is used until the unit gets calibrated.
+            inputs: [Calibration::new(0.02, 10.0 / (2.0 * 4.98)); INPUTS],
+            outputs: [Calibration::new(0.0, 1.0); OUTPUTS],
+        }
+    }
+}
+
+impl Calibration {
+    pub fn new(offset: f32, scale: f32) -> Self {
+        Self { offset, scale }
+    }
+
+    /// Anything this far off is a failed measurement rather than
+    /// a tolerance of components.
+    pub fn is_plausible(&self) -> bool {
+        self.offset.is_finite()
+            && self.scale.is_finite()
+            && libm::fabsf(self.offset) < 0.5
+            && (0.8..1.2).contains(&self.scale)
+    }
+}
+
+// NOTE: Voltages are stored in millivolts.
+fn voltage_to_u16(voltage: f32) -> u16 {
+    (voltage.clamp(0.0, 5.0) * 1000.0 + 0.5) as u16
+}
+
+fn u16_to_voltage(value: u16) -> f32 {
+    (value as f32 / 1000.0).min(5.0)
+}
+
+fn f32_from_le_bytes(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Fletcher-16 checksum.
+fn checksum(bytes: &[u8]) -> u16 {
+    let mut sum_1: u16 = 0;
+    let mut sum_2: u16 = 0;
+    for byte in bytes {
+        sum_1 = (sum_1 + *byte as u16) % 255;
+        sum_2 = (sum_2 + sum_1) % 255;
+    }
+    (sum_2 << 8) | sum_1
+}
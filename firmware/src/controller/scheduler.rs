@@ -0,0 +1,92 @@
+use heapless::Vec;
+
+use super::output::BinaryOutput;
+
+/// Schedules pulses to be sent to a binary output in the future.
+///
+/// Unlike `BinaryOutput::enable_with_countdown`, this can keep track of
+/// multiple pending pulses.
+pub struct PulseScheduler<const N: usize> {
+    pulses: Vec<Pulse, N>,
+}
+
+struct Pulse {
+    delay: usize,
+    length: usize,
+}
+
+impl<const N: usize> PulseScheduler<N> {
+    pub fn new() -> Self {
+        Self { pulses: Vec::new() }
+    }
+
+    /// Schedule a pulse starting after `delay` ticks and lasting `length`
+    /// ticks. If the scheduler is full, the pulse is dropped.
+    pub fn schedule(&mut self, delay: usize, length: usize) {
+        let _ = self.pulses.push(Pulse { delay, length });
+    }
+
+    pub fn clear(&mut self) {
+        self.pulses.clear();
+    }
+
+    pub fn tick(&mut self, output: &mut BinaryOutput) {
+        let mut i = 0;
+        while i < self.pulses.len() {
+            if self.pulses[i].delay == 0 {
+                output.enable_with_countdown(self.pulses[i].length);
+                self.pulses.swap_remove(i);
+            } else {
+                self.pulses[i].delay -= 1;
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Schedules changes of a binary output in the future.
+///
+/// This is used to delay whole gates, keeping their original length. Each
+/// scheduled edge sets the output high or low once its delay passes. Edges
+/// are applied in the order they were scheduled, an edge scheduled with a
+/// shorter delay than the one before it waits for it.
+pub struct EdgeScheduler<const N: usize> {
+    edges: Vec<Edge, N>,
+}
+
+struct Edge {
+    delay: usize,
+    high: bool,
+}
+
+impl<const N: usize> EdgeScheduler<N> {
+    pub fn new() -> Self {
+        Self { edges: Vec::new() }
+    }
+
+    /// Schedule the output to be set to `high` after `delay` ticks. If the
+    /// scheduler is full, the edge is dropped.
+    pub fn schedule(&mut self, delay: usize, high: bool) {
+        let delay = match self.edges.last() {
+            Some(last) => delay.max(last.delay),
+            None => delay,
+        };
+        let _ = self.edges.push(Edge { delay, high });
+    }
+
+    /// Drop all pending edges and set the output low.
+    pub fn clear(&mut self, output: &mut BinaryOutput) {
+        self.edges.clear();
+        output.set(false);
+    }
+
+    pub fn tick(&mut self, output: &mut BinaryOutput) {
+        while !self.edges.is_empty() && self.edges[0].delay == 0 {
+            output.set(self.edges[0].high);
+            self.edges.remove(0);
+        }
+        for edge in self.edges.iter_mut() {
+            edge.delay -= 1;
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Slew limiter with independent rise and fall times.
+
+pub struct Slew {
+    value: f32,
+    rise: f32,
+    fall: f32,
+    shape: Shape,
+    movement: Movement,
+    // NOTE: Distance from the target at the start of the current movement.
+    start: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// Constant rate of change.
+    Linear,
+    /// Fast start, slowing down as the target is approached.
+    Exponential,
+    /// Slow start, speeding up as the target is approached.
+    Logarithmic,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    Rising,
+    Falling,
+    Settled,
+}
+
+/// Reported when the slew reaches its target.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    EndOfRise,
+    EndOfFall,
+}
+
+impl Slew {
+    // NOTE: Times are defined as the time it takes to cover the full range
+    // of the CV input.
+    const RANGE: f32 = 10.0;
+    // NOTE: Differences below 10 mV are considered settled, so noise on
+    // the input does not keep starting new movements.
+    const EPSILON: f32 = 0.01;
+
+    pub fn new() -> Self {
+        Self {
+            value: 0.0,
+            rise: 0.0,
+            fall: 0.0,
+            shape: Shape::Linear,
+            movement: Movement::Settled,
+            start: 0.0,
+        }
+    }
+
+    /// Set rise and fall times in seconds.
+    pub fn set_times(&mut self, rise: f32, fall: f32) {
+        self.rise = rise;
+        self.fall = fall;
+    }
+
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn movement(&self) -> Movement {
+        self.movement
+    }
+
+    /// Move towards the target by one tick.
+    pub fn tick(&mut self, target: f32) -> Option<Event> {
+        let distance = target - self.value;
+        if libm::fabsf(distance) < Self::EPSILON {
+            self.value = target;
+            let event = match self.movement {
+                Movement::Rising => Some(Event::EndOfRise),
+                Movement::Falling => Some(Event::EndOfFall),
+                Movement::Settled => None,
+            };
+            self.movement = Movement::Settled;
+            return event;
+        }
+
+        let time = if distance > 0.0 { self.rise } else { self.fall };
+        let movement = if distance > 0.0 {
+            Movement::Rising
+        } else {
+            Movement::Falling
+        };
+        if movement != self.movement || libm::fabsf(distance) > self.start {
+            self.start = libm::fabsf(distance);
+        }
+        self.movement = movement;
+
+        let ticks = (time * super::CONTROL_RATE).max(1.0);
+        let step = match self.shape {
+            Shape::Linear => Self::RANGE / ticks,
+            Shape::Exponential => {
+                // NOTE: Gets within 1 % of the target in the given time.
+                libm::fabsf(distance) * (1.0 - libm::expf(-4.6 / ticks))
+            }
+            Shape::Logarithmic => {
+                // NOTE: The speed ramps from 0.1 to 2.0 of the linear one.
+                // The normalization keeps the total time equal to linear.
+                const NORMALIZATION: f32 = 1.5767;
+                let progress = 1.0 - libm::fabsf(distance) / self.start;
+                Self::RANGE / ticks * NORMALIZATION * (0.1 + 1.9 * progress)
+            }
+        };
+
+        if step >= libm::fabsf(distance) {
+            self.value = target;
+        } else {
+            self.value += libm::copysignf(step, distance);
+        }
+
+        None
+    }
+}
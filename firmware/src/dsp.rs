@@ -0,0 +1,44 @@
+use crate::audio::BLOCK_LENGTH;
+
+/// Audio processing, currently applying gain to each channel.
+pub struct Dsp {
+    gains: [f32; 2],
+    target_gains: [f32; 2],
+}
+
+pub struct Attributes {
+    pub gains: [f32; 2],
+}
+
+impl Dsp {
+    pub fn new() -> Self {
+        Self {
+            gains: [1.0, 1.0],
+            target_gains: [1.0, 1.0],
+        }
+    }
+
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        self.target_gains = attributes.gains;
+    }
+
+    pub fn process(&mut self, buffer: &mut [(f32, f32); BLOCK_LENGTH]) {
+        // NOTE: Gains are updated with the control rate. To prevent zipper
+        // noise, they are linearly interpolated through the block.
+        let step_l = (self.target_gains[0] - self.gains[0]) / BLOCK_LENGTH as f32;
+        let step_r = (self.target_gains[1] - self.gains[1]) / BLOCK_LENGTH as f32;
+        for (l, r) in buffer.iter_mut() {
+            self.gains[0] += step_l;
+            self.gains[1] += step_r;
+            *l *= self.gains[0];
+            *r *= self.gains[1];
+        }
+        self.gains = self.target_gains;
+    }
+}
+
+impl Default for Dsp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -8,9 +8,12 @@ use stm32h7xx_hal as _;
 pub mod audio;
 pub mod control_input;
 pub mod control_output;
+pub mod controller;
+pub mod dsp;
 pub mod queue_utils;
 pub mod random_generator;
 pub mod startup_sequence;
+pub mod storage;
 pub mod system;
 
 // Same panicking *behavior* as `panic-probe` but doesn't print a panic message
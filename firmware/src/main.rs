@@ -12,159 +12,24 @@ mod app {
     use systick_monotonic::Systick;
 
     use handy_firmware::audio::{AudioInterface, SAMPLE_RATE};
-    use handy_firmware::control_input::{ControlInputInterface, ControlInputSnapshot};
-    use handy_firmware::control_output::{ControlOutputInterface, ControlOutputState};
+    use handy_firmware::control_input::{
+        handle_gates_interrupt, ControlInputInterface, ControlInputSnapshot,
+    };
+    use handy_firmware::control_output::ControlOutputInterface;
+    use handy_firmware::controller::{Calibrations, Controller, Save};
+    use handy_firmware::dsp::{Attributes as DspAttributes, Dsp};
     use handy_firmware::queue_utils;
     use handy_firmware::random_generator::RandomGenerator;
     use handy_firmware::startup_sequence;
+    use handy_firmware::storage::Storage;
     use handy_firmware::system::System;
 
     // TODO:
     // - [X] CV generator steady
-    // - [ ] CV generator sine
+    // - [X] CV generator sine
     // - [X] Attenuator
     // - [ ] Saw VCO
 
-    struct Dsp {}
-    struct DspAttributes {}
-
-    struct Controller {
-        clock_1_phase: f32,
-        clock_1_speed: f32,
-        clock_2_phase: u8,
-        clock_2_division: u8,
-        attenuation_input: f32,
-        attenuation: f32,
-        cv_generator_steady: f32,
-        leds: [BinaryOutput; 4],
-        gates: [BinaryOutput; 2],
-        cvs: [LinearOutput; 2],
-    }
-
-    struct BinaryOutput {
-        on: bool,
-        countdown: usize,
-    }
-
-    struct LinearOutput {
-        value: f32,
-    }
-
-    impl Controller {
-        pub fn new() -> Self {
-            Self {
-                clock_1_phase: 0.0,
-                clock_1_speed: 0.001,
-                clock_2_phase: 0,
-                clock_2_division: 1,
-                attenuation_input: 0.0,
-                attenuation: 0.0,
-                cv_generator_steady: 0.0,
-                leds: [
-                    BinaryOutput::new(),
-                    BinaryOutput::new(),
-                    BinaryOutput::new(),
-                    BinaryOutput::new(),
-                ],
-                gates: [BinaryOutput::new(), BinaryOutput::new()],
-                cvs: [LinearOutput::new(), LinearOutput::new()],
-            }
-        }
-
-        pub fn apply_input_snapshot(&mut self, snapshot: ControlInputSnapshot) {
-            // Pot should move from output every 100 ms to every 2000 ms
-            // Meaning the speed (revolutions per second) should be between 100 and 0.5.
-            const MIN: f32 = 0.5;
-            const MAX: f32 = 10.0;
-            self.clock_1_speed = MIN + snapshot.pots[1] * (MAX - MIN);
-            self.clock_2_division = 1 + (snapshot.pots[0] * 8.99) as u8;
-
-            self.attenuation = snapshot.pots[2];
-            self.attenuation_input = snapshot.cvs[2].unwrap_or(0.0);
-
-            self.cv_generator_steady = snapshot.pots[3] * 5.0;
-        }
-
-        pub fn tick(&mut self) -> ControlOutputState {
-            // NOTE: For control SR of 1 kHz.
-            self.clock_1_phase += self.clock_1_speed / 1000.0;
-            if self.clock_1_phase >= 1.0 {
-                (self.clock_1_phase, _) = libm::modff(self.clock_1_phase);
-                self.clock_2_phase += 1;
-                self.leds[0].enable_with_countdown(30);
-                self.gates[0].enable_with_countdown(10);
-            }
-
-            if self.clock_2_phase >= self.clock_2_division {
-                self.clock_2_phase = 0;
-                self.leds[1].enable_with_countdown(30);
-                self.gates[1].enable_with_countdown(10);
-            }
-
-            self.cvs[0].set_value(self.cv_generator_steady);
-            self.cvs[1].set_value(self.attenuation_input * self.attenuation);
-
-            self.leds[0].tick();
-            self.leds[1].tick();
-            self.leds[2].tick();
-            self.leds[3].tick();
-            self.gates[0].tick();
-            self.gates[1].tick();
-
-            ControlOutputState {
-                leds: [
-                    self.leds[0].value(),
-                    self.leds[1].value(),
-                    self.leds[2].value(),
-                    self.leds[3].value(),
-                ],
-                gates: [self.gates[0].value(), self.gates[1].value()],
-                cvs: [self.cvs[0].value(), self.cvs[1].value()],
-            }
-        }
-    }
-
-    impl BinaryOutput {
-        pub fn new() -> Self {
-            Self {
-                on: false,
-                countdown: 0,
-            }
-        }
-
-        pub fn tick(&mut self) {
-            if self.countdown > 0 {
-                self.countdown -= 1;
-                if self.countdown == 0 {
-                    self.on = false;
-                }
-            }
-        }
-
-        pub fn value(&self) -> bool {
-            self.on
-        }
-
-        pub fn enable_with_countdown(&mut self, countdown: usize) {
-            self.on = true;
-            self.countdown = countdown;
-        }
-    }
-
-    impl LinearOutput {
-        pub fn new() -> Self {
-            Self { value: 0.0 }
-        }
-
-        pub fn set_value(&mut self, value: f32) {
-            self.value = value;
-        }
-
-        pub fn value(&self) -> f32 {
-            self.value
-        }
-    }
-
     #[link_section = ".sram"]
     static mut MEMORY: [MaybeUninit<u32>; 96 * 1024] =
         unsafe { MaybeUninit::uninit().assume_init() };
@@ -184,16 +49,20 @@ mod app {
         control_output_interface: ControlOutputInterface,
         dsp: Dsp,
         controller: Controller,
+        storage: Storage,
         dsp_attributes_producer: Producer<'static, DspAttributes, 8>,
         dsp_attributes_consumer: Consumer<'static, DspAttributes, 8>,
         control_input_snapshot_producer: Producer<'static, ControlInputSnapshot, 8>,
         control_input_snapshot_consumer: Consumer<'static, ControlInputSnapshot, 8>,
+        calibrations_producer: Producer<'static, Calibrations, 2>,
+        calibrations_consumer: Consumer<'static, Calibrations, 2>,
     }
 
     #[init(
         local = [
             dsp_attributes_queue: Queue<DspAttributes, 8> = Queue::new(),
             input_snapshot_queue: Queue<ControlInputSnapshot, 8> = Queue::new(),
+            calibrations_queue: Queue<Calibrations, 2> = Queue::new(),
         ]
     )]
     fn init(mut cx: init::Context) -> (Shared, Local, init::Monotonics) {
@@ -208,20 +77,25 @@ mod app {
             cx.local.dsp_attributes_queue.split();
         let (control_input_snapshot_producer, control_input_snapshot_consumer) =
             cx.local.input_snapshot_queue.split();
+        let (calibrations_producer, calibrations_consumer) = cx.local.calibrations_queue.split();
 
         let system = System::init(cx.core, cx.device);
         let mono = system.mono;
-        let mut random_generator = system.random_generator;
+        let random_generator = system.random_generator;
         let mut audio_interface = system.audio_interface;
         let mut control_input_interface = system.control_input_interface;
-        let control_output_interface = system.control_output_interface;
+        let mut control_output_interface = system.control_output_interface;
+        let mut storage = system.storage;
 
+        let save = storage.load_save();
+        apply_input_calibrations(&mut control_input_interface, &save.calibrations);
+        apply_output_calibrations(&mut control_output_interface, &save.calibrations);
         startup_sequence::warm_up_control_input(&mut control_input_interface);
-        // let controller = Controller::new(seed, save);
-        // let mut stack_manager = MemoryManager::from(unsafe { &mut MEMORY[..] });
-        // let dsp = Dsp::new(SAMPLE_RATE as f32, &mut stack_manager);
-        let controller = Controller::new();
-        let dsp = Dsp {};
+        // SAFETY: The memory is borrowed only once, here, during the
+        // initialization.
+        let memory = unsafe { &mut *core::ptr::addr_of_mut!(MEMORY) };
+        let controller = Controller::new(save, memory);
+        let dsp = Dsp::new();
 
         defmt::info!("Spawning tasks");
 
@@ -238,10 +112,13 @@ mod app {
                 control_output_interface,
                 dsp,
                 controller,
+                storage,
                 dsp_attributes_producer,
                 dsp_attributes_consumer,
                 control_input_snapshot_producer,
                 control_input_snapshot_consumer,
+                calibrations_producer,
+                calibrations_consumer,
             },
             init::Monotonics(mono),
         )
@@ -251,12 +128,18 @@ mod app {
         local = [
             control_input_interface,
             control_input_snapshot_producer,
+            calibrations_consumer,
         ],
         priority = 2,
     )]
     fn input_collection_loop(cx: input_collection_loop::Context) {
         let control_input_interface = cx.local.control_input_interface;
         let control_input_snapshot_producer = cx.local.control_input_snapshot_producer;
+        let calibrations_consumer = cx.local.calibrations_consumer;
+
+        if let Some(calibrations) = queue_utils::dequeue_last(calibrations_consumer) {
+            apply_input_calibrations(control_input_interface, &calibrations);
+        }
 
         control_input_interface.sample();
 
@@ -268,12 +151,21 @@ mod app {
         input_collection_loop::spawn_after(1.millis()).ok().unwrap();
     }
 
+    // NOTE: Gate edges are latched with the highest priority, so they get
+    // registered even while other tasks are running.
+    #[task(binds = EXTI15_10, priority = 5)]
+    fn gate_edge(_: gate_edge::Context) {
+        handle_gates_interrupt();
+    }
+
     #[task(
         local = [
             controller,
+            random_generator,
             control_output_interface,
             dsp_attributes_producer,
             control_input_snapshot_consumer,
+            calibrations_producer,
         ],
         priority = 3,
     )]
@@ -281,26 +173,62 @@ mod app {
         control_loop::spawn_after(1.millis()).ok().unwrap();
 
         let controller = cx.local.controller;
+        let random_generator = cx.local.random_generator;
         let control_output_interface = cx.local.control_output_interface;
         let dsp_attributes_producer = cx.local.dsp_attributes_producer;
         let control_input_snapshot_consumer = cx.local.control_input_snapshot_consumer;
+        let calibrations_producer = cx.local.calibrations_producer;
 
         queue_utils::warn_about_capacity("input_snapshot", control_input_snapshot_consumer);
 
-        if let Some(snapshot) = queue_utils::dequeue_last(control_input_snapshot_consumer) {
-            let result = controller.apply_input_snapshot(snapshot);
-            // let _ = dsp_attributes_producer.enqueue(result.dsp_attributes);
+        // NOTE: Only the latest snapshot is applied, but gate triggers of
+        // all the skipped ones are carried over to it, so no edge is lost
+        // when the loop falls behind.
+        let mut last_snapshot: Option<ControlInputSnapshot> = None;
+        let mut gate_triggers = [false; 2];
+        while let Some(snapshot) = control_input_snapshot_consumer.dequeue() {
+            for (trigger, new) in gate_triggers.iter_mut().zip(snapshot.gate_triggers) {
+                *trigger |= new;
+            }
+            last_snapshot = Some(snapshot);
+        }
+        if let Some(mut snapshot) = last_snapshot {
+            snapshot.gate_triggers = gate_triggers;
+            controller.apply_input_snapshot(snapshot);
         }
 
-        let desired_output_state = controller.tick();
+        let desired_output_state = controller.tick(random_generator);
         control_output_interface.set_state(&desired_output_state);
+
+        let _ = dsp_attributes_producer.enqueue(DspAttributes {
+            gains: controller.audio_gains(),
+        });
+
+        if let Some(calibrations) = controller.pending_calibrations() {
+            apply_output_calibrations(control_output_interface, &calibrations);
+            let _ = calibrations_producer.enqueue(calibrations);
+        }
+
+        if let Some(save) = controller.pending_save() {
+            // NOTE: The previous save may still be getting stored. Try again
+            // on the next tick, so the latest state is not lost.
+            if store_save::spawn(save).is_err() {
+                controller.request_save();
+            }
+        }
+    }
+
+    // NOTE: Erasing flash takes a while, so this runs on the lowest priority,
+    // not to block the control loop.
+    #[task(local = [storage], priority = 1, capacity = 1)]
+    fn store_save(cx: store_save::Context, save: Save) {
+        cx.local.storage.save_save(&save);
     }
 
     #[task(
         binds = DMA1_STR1,
         local = [
             audio_interface,
-            random_generator,
             dsp,
             dsp_attributes_consumer,
         ],
@@ -308,18 +236,17 @@ mod app {
     )]
     fn dsp_loop(cx: dsp_loop::Context) {
         let audio_interface = cx.local.audio_interface;
-        // let random_generator = cx.local.random_generator;
-        // let dsp = cx.local.dsp;
+        let dsp = cx.local.dsp;
         let dsp_attributes_consumer = cx.local.dsp_attributes_consumer;
 
         queue_utils::warn_about_capacity("dsp_attributes", dsp_attributes_consumer);
 
         if let Some(attributes) = queue_utils::dequeue_last(dsp_attributes_consumer) {
-            // dsp.set_attributes(attributes);
+            dsp.set_attributes(attributes);
         }
 
         audio_interface.update_buffer(|buffer| {
-            // dsp.process(buffer, random_generator);
+            dsp.process(buffer);
         });
     }
 
@@ -368,6 +295,24 @@ mod app {
         }
     }
 
+    fn apply_input_calibrations(
+        control_input_interface: &mut ControlInputInterface,
+        calibrations: &Calibrations,
+    ) {
+        for (i, calibration) in calibrations.inputs.iter().enumerate() {
+            control_input_interface.set_cv_calibration(i, calibration.offset, calibration.scale);
+        }
+    }
+
+    fn apply_output_calibrations(
+        control_output_interface: &mut ControlOutputInterface,
+        calibrations: &Calibrations,
+    ) {
+        for (i, calibration) in calibrations.outputs.iter().enumerate() {
+            control_output_interface.set_cv_calibration(i, calibration.offset, calibration.scale);
+        }
+    }
+
     fn calculate_elapsed_dwt_ticks(now: u32, start: &mut u32) -> u32 {
         if now >= *start {
             now - *start
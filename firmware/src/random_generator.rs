@@ -13,4 +13,11 @@ impl RandomGenerator {
         use daisy::hal::rng::RngCore;
         RngCore::<u16>::gen(&mut self.rng)
     }
+
+    /// Random number between 0.0 and 1.0.
+    ///
+    /// Failures of the generator are ignored, returning 0.0.
+    pub fn f32(&mut self) -> f32 {
+        self.u16().unwrap_or_default() as f32 / u16::MAX as f32
+    }
 }
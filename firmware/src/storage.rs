@@ -0,0 +1,42 @@
+//! Persistent storage of the controller state on the external QSPI flash.
+
+use daisy::flash::{Flash, FlashErase};
+
+use crate::controller::{Save, SAVE_SIZE};
+
+// NOTE: The save occupies the first 4 kB sector of the flash.
+const ADDRESS: u32 = 0;
+// NOTE: Flash can be programmed by at most a single page at once.
+const PAGE_SIZE: usize = 256;
+
+pub struct Storage {
+    flash: Flash,
+}
+
+impl Storage {
+    pub fn new(flash: Flash) -> Self {
+        Self { flash }
+    }
+
+    /// Load the last stored save, falling back to default if there is none.
+    pub fn load_save(&mut self) -> Save {
+        let mut bytes = [0; SAVE_SIZE];
+        self.flash.read(ADDRESS, &mut bytes);
+        Save::from_bytes(&bytes).unwrap_or_else(|| {
+            defmt::info!("No valid save found, using defaults");
+            Save::default()
+        })
+    }
+
+    /// Store the save, replacing the previous one.
+    ///
+    /// This blocks for tens of milliseconds while the sector gets erased,
+    /// it must not be called from time-critical tasks.
+    pub fn save_save(&mut self, save: &Save) {
+        let bytes = save.to_bytes();
+        self.flash.erase(FlashErase::Sector4K(ADDRESS));
+        for (i, page) in bytes.chunks(PAGE_SIZE).enumerate() {
+            self.flash.program(ADDRESS + (i * PAGE_SIZE) as u32, page);
+        }
+    }
+}
@@ -4,6 +4,7 @@ use daisy::led::LedUser;
 use fugit::Hertz;
 use hal::adc::{AdcSampleTime, Resolution};
 use hal::delay::DelayFromCountDownTimer;
+use hal::gpio::{Edge, ExtiPin};
 use hal::pac::CorePeripherals;
 use hal::pac::Peripherals as DevicePeripherals;
 use hal::prelude::*;
@@ -19,6 +20,7 @@ use crate::control_output::{
     Config as ControlOutputConfig, ControlOutputInterface, Pins as ControlOutputPins,
 };
 use crate::random_generator::RandomGenerator;
+use crate::storage::Storage;
 
 pub struct System {
     pub frequency: Hertz<u32>,
@@ -28,6 +30,7 @@ pub struct System {
     pub audio_interface: AudioInterface,
     pub control_input_interface: ControlInputInterface,
     pub control_output_interface: ControlOutputInterface,
+    pub storage: Storage,
 }
 
 impl System {
@@ -36,7 +39,7 @@ impl System {
     /// # Panics
     ///
     /// The system can be initialized only once. It panics otherwise.
-    pub fn init(mut cp: CorePeripherals, dp: DevicePeripherals) -> Self {
+    pub fn init(mut cp: CorePeripherals, mut dp: DevicePeripherals) -> Self {
         enable_cache(&mut cp);
 
         let board = daisy::Board::take().unwrap();
@@ -49,12 +52,27 @@ impl System {
         let random_generator =
             RandomGenerator::from_rng(dp.RNG.constrain(ccdr.peripheral.RNG, &ccdr.clocks));
         let audio_interface = AudioInterface::new(daisy::board_split_audio!(ccdr, pins));
+        let storage = Storage::new(daisy::board_split_flash!(ccdr, dp, pins));
         let mut delay = DelayFromCountDownTimer::new(dp.TIM2.timer(
             100.Hz(),
             ccdr.peripheral.TIM2,
             &ccdr.clocks,
         ));
         let control_input_interface = {
+            let gate_1 = {
+                let mut pin = pins.GPIO.PIN_B10.into_floating_input();
+                pin.make_interrupt_source(&mut dp.SYSCFG);
+                pin.trigger_on_edge(&mut dp.EXTI, Edge::Rising);
+                pin.enable_interrupt(&mut dp.EXTI);
+                pin
+            };
+            let gate_2 = {
+                let mut pin = pins.GPIO.PIN_B9.into_floating_input();
+                pin.make_interrupt_source(&mut dp.SYSCFG);
+                pin.trigger_on_edge(&mut dp.EXTI, Edge::Rising);
+                pin.enable_interrupt(&mut dp.EXTI);
+                pin
+            };
             let (adc_1, adc_2) = {
                 let (mut adc_1, mut adc_2) = hal::adc::adc12(
                     dp.ADC1,
@@ -87,10 +105,7 @@ impl System {
                     cv_3: pins.GPIO.PIN_C3.into_analog(),
                     cv_4: pins.GPIO.PIN_C2.into_analog(),
                 },
-                gates_pins: ControlInputGatesPins {
-                    gate_1: pins.GPIO.PIN_B10.into_floating_input(),
-                    gate_2: pins.GPIO.PIN_B9.into_floating_input(),
-                },
+                gates_pins: ControlInputGatesPins { gate_1, gate_2 },
                 switch_pins: ControlInputSwitchPins {
                     switch_1: pins.GPIO.PIN_D6.into_pull_up_input(),
                     switch_2: pins.GPIO.PIN_D7.into_pull_up_input(),
@@ -136,6 +151,7 @@ impl System {
             audio_interface,
             control_input_interface,
             control_output_interface,
+            storage,
         }
     }
 }